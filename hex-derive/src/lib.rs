@@ -0,0 +1,216 @@
+//! `#[derive(HexTool)]` for hex tools.
+//!
+//! Checks at compile time that the tool and every argument are described,
+//! and generates the `Deserialize` and `JsonSchema` impls that the toolset
+//! needs, with the tool name set independently of the type name:
+//!
+//! ```ignore
+//! /// Read one or more files
+//! #[derive(HexTool)]
+//! #[tool(name = "read_files")]
+//! pub struct ReadFiles {
+//!     /// relative paths to read
+//!     pub paths: Vec<String>,
+//! }
+//! ```
+//!
+//! `serde` and `schemars` attributes are passed through to the generated
+//! impls. The expansion refers to `::hex`, so the `hex` crate must be a
+//! dependency.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Error, Expr, Fields, Lit, LitStr, parse_macro_input};
+
+#[proc_macro_derive(HexTool, attributes(tool, serde, schemars))]
+pub fn derive_hex_tool(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ToolAttrs {
+    name: Option<LitStr>,
+    description: Option<LitStr>,
+}
+
+impl ToolAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = ToolAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("tool")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    parsed.name = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("description") {
+                    parsed.description = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `name` or `description`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "HexTool does not support generic types",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            Fields::Unit => vec![],
+            Fields::Unnamed(_) => {
+                return Err(Error::new(
+                    ident.span(),
+                    "HexTool arguments need named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                ident.span(),
+                "HexTool can only be derived for structs",
+            ));
+        }
+    };
+
+    let attrs = ToolAttrs::parse(&input.attrs)?;
+    let name = attrs
+        .name
+        .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+    let valid_name = !name.value().is_empty()
+        && name.value().len() <= 64
+        && name
+            .value()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_name {
+        return Err(Error::new(
+            name.span(),
+            "tool names must be 1-64 characters of a-z, A-Z, 0-9, `_` or `-`",
+        ));
+    }
+    let description = doc_description(&input.attrs, attrs.description).ok_or_else(|| {
+        Error::new(
+            ident.span(),
+            format!(
+                "tool `{ident}` needs a description: add a doc comment or #[tool(description = \"...\")]"
+            ),
+        )
+    })?;
+
+    let mut shadow_fields = vec![];
+    let mut field_names = vec![];
+    for field in &fields {
+        let field_ident = field.ident.as_ref().expect("named field");
+        let field_attrs = ToolAttrs::parse(&field.attrs)?;
+        if let Some(name) = field_attrs.name {
+            return Err(Error::new(
+                name.span(),
+                "use #[serde(rename = \"...\")] to rename an argument",
+            ));
+        }
+        let field_description =
+            doc_description(&field.attrs, field_attrs.description).ok_or_else(|| {
+                Error::new(
+                    field_ident.span(),
+                    format!(
+                        "argument `{field_ident}` needs a description: add a doc comment or #[tool(description = \"...\")]"
+                    ),
+                )
+            })?;
+        let forwarded = forwarded_attrs(&field.attrs);
+        let ty = &field.ty;
+        shadow_fields.push(quote! {
+            #(#forwarded)*
+            #[schemars(description = #field_description)]
+            #field_ident: #ty
+        });
+        field_names.push(field_ident);
+    }
+    let container_attrs = forwarded_attrs(&input.attrs);
+
+    Ok(quote! {
+        const _: () = {
+            use ::hex::__private::{schemars, serde};
+
+            #[derive(serde::Deserialize, schemars::JsonSchema)]
+            #[serde(crate = "::hex::__private::serde")]
+            #[schemars(
+                crate = "::hex::__private::schemars",
+                rename = #name,
+                title = #name,
+                description = #description
+            )]
+            #(#container_attrs)*
+            struct HexToolArguments {
+                #(#shadow_fields,)*
+            }
+
+            impl<'de> serde::Deserialize<'de> for #ident {
+                fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    let HexToolArguments { #(#field_names),* } =
+                        <HexToolArguments as serde::Deserialize>::deserialize(deserializer)?;
+                    ::core::result::Result::Ok(#ident { #(#field_names),* })
+                }
+            }
+
+            impl schemars::JsonSchema for #ident {
+                fn schema_name() -> ::std::string::String {
+                    <HexToolArguments as schemars::JsonSchema>::schema_name()
+                }
+
+                fn json_schema(
+                    generator: &mut schemars::r#gen::SchemaGenerator,
+                ) -> schemars::schema::Schema {
+                    <HexToolArguments as schemars::JsonSchema>::json_schema(generator)
+                }
+            }
+        };
+    })
+}
+
+/// An explicit `#[tool(description)]`, falling back to the doc comment.
+fn doc_description(attrs: &[Attribute], explicit: Option<LitStr>) -> Option<String> {
+    if let Some(explicit) = explicit {
+        return Some(explicit.value()).filter(|text| !text.trim().is_empty());
+    }
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(expr) => match &expr.lit {
+                Lit::Str(text) => Some(text.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let text = lines
+        .split(String::is_empty)
+        .map(|paragraph| paragraph.join(" "))
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(text).filter(|text| !text.is_empty())
+}
+
+fn forwarded_attrs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde") || attr.path().is_ident("schemars"))
+        .collect()
+}
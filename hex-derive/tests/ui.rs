@@ -0,0 +1,4 @@
+#[test]
+fn reports_invalid_tools() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,11 @@
+use hex_derive::HexTool;
+
+/// Read some files
+#[derive(HexTool)]
+#[tool(name = "read files")]
+struct ReadFiles {
+    /// the paths to read
+    paths: Vec<String>,
+}
+
+fn main() {}
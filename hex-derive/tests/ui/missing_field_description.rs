@@ -0,0 +1,9 @@
+use hex_derive::HexTool;
+
+/// Shout some text
+#[derive(HexTool)]
+struct Shout {
+    text: String,
+}
+
+fn main() {}
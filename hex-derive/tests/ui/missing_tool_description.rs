@@ -0,0 +1,9 @@
+use hex_derive::HexTool;
+
+#[derive(HexTool)]
+struct Undescribed {
+    /// the text to use
+    text: String,
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+mod policy;
+mod unattended;
+
+pub use policy::*;
+pub use unattended::*;
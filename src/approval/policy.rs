@@ -0,0 +1,95 @@
+use crate::config::ApprovalPolicy;
+use crate::types::{Approval, ApprovalRequest, Approver};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use glob::{MatchOptions, Pattern};
+use std::path::{Component, Path};
+
+/// Applies an [`ApprovalPolicy`] and a path allowlist, and hands every call
+/// they don't settle to `ask`, e.g. a terminal prompt or a web dialog.
+///
+/// - `ask` asks before every call.
+/// - `auto-approve-reads` runs calls to tools not marked as mutating.
+/// - `deny-all` refuses every mutating call; reads still run.
+///
+/// Under the first two, a call whose paths all match an allowed glob runs
+/// without asking.
+pub struct PolicyApprover<A> {
+    policy: ApprovalPolicy,
+    allowed_paths: Vec<Pattern>,
+    ask: A,
+}
+
+impl<A: Approver> PolicyApprover<A> {
+    pub fn new(policy: ApprovalPolicy, ask: A) -> Self {
+        Self {
+            policy,
+            allowed_paths: vec![],
+            ask,
+        }
+    }
+
+    /// Globs such as `src/**` or `docs/*.md`, matched against paths relative
+    /// to the working directory. Paths outside of it are never allowed, even
+    /// by `**`.
+    pub fn with_allowed_paths<S: AsRef<str>>(mut self, globs: &[S]) -> Result<Self> {
+        for glob in globs {
+            let glob = glob.as_ref();
+            let pattern =
+                Pattern::new(glob).with_context(|| format!("invalid path glob '{glob}'"))?;
+            self.allowed_paths.push(pattern);
+        }
+        Ok(self)
+    }
+
+    fn is_allowed(&self, path: &str) -> bool {
+        let path = Path::new(path.strip_prefix("./").unwrap_or(path));
+        let path = if path.has_root() {
+            let cwd = std::env::current_dir().unwrap_or_default();
+            match path.strip_prefix(&cwd) {
+                Ok(relative) => relative,
+                Err(_) => return false,
+            }
+        } else {
+            path
+        };
+        // roots, drive prefixes and `..` could step out of any allowed
+        // directory
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return false;
+        }
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        self.allowed_paths
+            .iter()
+            .any(|pattern| pattern.matches_path_with(path, options))
+    }
+}
+
+#[async_trait]
+impl<A: Approver> Approver for PolicyApprover<A> {
+    async fn approve(&self, request: &ApprovalRequest) -> Result<Approval> {
+        match self.policy {
+            ApprovalPolicy::DenyAll if request.mutating => {
+                return Ok(Approval::Denied(
+                    "the approval policy does not allow changes".to_string(),
+                ));
+            }
+            ApprovalPolicy::DenyAll | ApprovalPolicy::AutoApproveReads if !request.mutating => {
+                return Ok(Approval::Approved);
+            }
+            _ => {}
+        }
+        let paths = request.paths();
+        if !paths.is_empty() && paths.iter().all(|path| self.is_allowed(path)) {
+            return Ok(Approval::Approved);
+        }
+        self.ask.approve(request).await
+    }
+}
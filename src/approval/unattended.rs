@@ -0,0 +1,19 @@
+use crate::types::{Approval, ApprovalRequest, Approver};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Denies every call it is asked about, for places where no one can answer,
+/// such as `hex mcp-serve`. Wrap it in a [`PolicyApprover`](super::PolicyApprover)
+/// so the policy and path allowlist still let calls through.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unattended;
+
+#[async_trait]
+impl Approver for Unattended {
+    async fn approve(&self, _request: &ApprovalRequest) -> Result<Approval> {
+        Ok(Approval::Denied(
+            "no one is available to approve this call".to_string(),
+        ))
+    }
+}
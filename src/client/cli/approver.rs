@@ -0,0 +1,37 @@
+use super::io::read_line;
+
+use crate::types::{Approval, ApprovalRequest, Approver};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Asks on the terminal: the question goes to stderr, so that it never mixes
+/// with piped output, and the answer is read from stdin. Calls running at once
+/// are asked about one at a time.
+#[derive(Default)]
+pub struct CliApprover {
+    asking: Mutex<()>,
+}
+
+#[async_trait]
+impl Approver for CliApprover {
+    async fn approve(&self, request: &ApprovalRequest) -> Result<Approval> {
+        let _asking = self.asking.lock().await;
+        let paths = request.paths();
+        let question = if paths.is_empty() {
+            format!("Allow {}({})? [y/N]: ", request.tool, request.arguments)
+        } else {
+            format!("Allow {} on {}? [y/N]: ", request.tool, paths.join(", "))
+        };
+        let mut stderr = tokio::io::stderr();
+        stderr.write_all(question.as_bytes()).await?;
+        stderr.flush().await?;
+        Ok(match read_line().await? {
+            Some(answer) if answer.trim().eq_ignore_ascii_case("y") => Approval::Approved,
+            Some(_) => Approval::Denied("the user declined".to_string()),
+            None => Approval::Denied("no one was there to approve the call".to_string()),
+        })
+    }
+}
@@ -1,218 +1,182 @@
-use super::io::{read_user_input, stdout_stream};
+use super::approver::CliApprover;
+use super::io::{drain_stream, pipeline_stream, read_piped_input, read_user_input, stdout_stream};
 
+use crate::approval::PolicyApprover;
+use crate::config::ApprovalPolicy;
+use crate::conversation::Conversation;
+use crate::mcp::McpServers;
+use crate::provider::{ChatProvider, Message, OpenAIProvider};
+use crate::session::Session;
 use crate::types::NoContext;
 use crate::types::SyncToolSet;
 
-use anyhow::{Context, Result};
-use async_openai::Client;
-use async_openai::config::OpenAIConfig;
-use async_openai::types::{
-    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
-    ChatCompletionRequestAssistantMessageContent, ChatCompletionRequestMessage,
-    ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
-    ChatCompletionRequestUserMessageContent, ChatCompletionResponseStream,
-    CreateChatCompletionRequestArgs, FinishReason, FunctionCall,
-};
-use async_stream::stream;
-use futures::stream::{Stream, StreamExt};
-use std::collections::HashMap;
-use std::pin::Pin;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use tokio::task::JoinHandle;
 
-pub struct CliClient {
-    inner: Client<OpenAIConfig>,
-    toolset: SyncToolSet<NoContext>,
-    messages: Vec<ChatCompletionRequestMessage>,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Stream the response as plain text
+    #[default]
+    Text,
+    /// Print one JSON object with the response and every new message
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct TurnOutput<'a> {
+    content: Option<&'a str>,
+    messages: &'a [Message],
+    error: Option<String>,
 }
 
-#[derive(Debug, Default)]
-pub struct ToolCal {
-    pub id: String,
-    pub name: String,
-    pub json: String,
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
-#[derive(Debug)]
-pub enum StreamPart {
-    Content(String),
-    ToolCall(ToolCal),
+pub struct CliClient {
+    conversation: Conversation<NoContext>,
+    session: Option<Session>,
+    mcp: McpServers,
 }
 
 impl CliClient {
     pub fn new(toolset: SyncToolSet<NoContext>) -> CliClient {
-        let client = Client::new();
+        Self::with_provider(Box::new(OpenAIProvider::new()), toolset)
+    }
+
+    /// Asks on the terminal before mutating tools run, as the default
+    /// approval policy does.
+    pub fn with_provider(
+        provider: Box<dyn ChatProvider>,
+        toolset: SyncToolSet<NoContext>,
+    ) -> CliClient {
+        let approver = PolicyApprover::new(ApprovalPolicy::default(), CliApprover::default());
+        Self::from_conversation(Conversation::new(
+            provider,
+            toolset.into_async().with_approver(approver),
+            NoContext(),
+        ))
+    }
+
+    pub fn from_conversation(conversation: Conversation<NoContext>) -> CliClient {
         CliClient {
-            inner: client,
-            toolset,
-            messages: vec![],
+            conversation,
+            session: None,
+            mcp: McpServers::default(),
         }
     }
 
-    pub async fn chat(&mut self) -> Result<()> {
-        print!("\x1B[2J\x1B[1;1H");
-        loop {
-            let user_input = read_user_input().await?;
+    /// Records every turn to `session`'s transcript.
+    pub fn with_session(mut self, session: Session) -> CliClient {
+        self.session = Some(session);
+        self
+    }
 
-            self.messages.push(ChatCompletionRequestMessage::User(
-                ChatCompletionRequestUserMessage {
-                    content: ChatCompletionRequestUserMessageContent::Text(user_input),
-                    name: None,
-                },
-            ));
+    /// Keeps the tools of `servers` up to date between chat messages. The
+    /// tools must already be registered in the conversation's toolset.
+    pub fn with_mcp(mut self, servers: McpServers) -> CliClient {
+        self.mcp = servers;
+        self
+    }
 
-            self.chat_response().await?;
-        }
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
     }
-}
 
-impl CliClient {
-    async fn chat_response(&mut self) -> Result<()> {
-        let stream = self.send_chat_message().await.context("bad request")?;
-        let stream = Self::parse_stream(stream);
-        let contents = stdout_stream(stream).await?;
-        let mut new_messages = self.process_stream(contents);
-        if new_messages
-            .iter()
-            .any(|message| matches!(message, ChatCompletionRequestMessage::Tool(..)))
-        {
-            new_messages.iter().for_each(|message| {
-                if let ChatCompletionRequestMessage::Assistant(message) = message {
-                    for call in message.tool_calls.as_deref().unwrap_or_default() {
-                        println!("[{}({})]", call.function.name, call.function.arguments);
-                    }
-                }
-            });
-            self.messages.append(&mut new_messages);
-            Box::pin(self.chat_response()).await
-        } else {
-            println!();
-            self.messages.append(&mut new_messages);
-            Ok(())
+    fn record_since(&mut self, start: usize) -> Result<()> {
+        match &mut self.session {
+            Some(session) => session.append(&self.conversation.messages()[start..]),
+            None => Ok(()),
         }
     }
 
-    async fn send_chat_message(&mut self) -> Result<ChatCompletionResponseStream> {
-        let request = CreateChatCompletionRequestArgs::default()
-            .model("gpt-4.1")
-            .messages(self.messages.clone())
-            .tools(self.toolset.openai_chatcompletion_toolset())
-            .n(1)
-            .build()?;
-
-        self.inner
-            .chat()
-            .create_stream(request)
-            .await
-            .map_err(anyhow::Error::from)
+    /// Makes Ctrl-C cancel the running tool calls instead of exiting. With
+    /// no tool running it exits as usual.
+    fn cancel_tools_on_ctrl_c(&self) -> AbortOnDrop {
+        let canceller = self.conversation.tool_canceller();
+        AbortOnDrop(tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if canceller.cancel() {
+                    eprintln!("\ncancelled the running tools");
+                } else {
+                    std::process::exit(130);
+                }
+            }
+        }))
     }
 
-    fn parse_stream(
-        mut stream: ChatCompletionResponseStream,
-    ) -> Pin<Box<dyn Stream<Item = Result<StreamPart>>>> {
-        Box::pin(stream! {
-        let mut tool_calls: HashMap<u32, ToolCal> = HashMap::new();
-        while let Some(part) = stream.next().await {
-          match part {
-            Ok(part) => {
-              let first = part.choices.first();
-              if first.is_none() {
-                  continue;
-              }
-              let first = first.unwrap();
-              if let Some(content) = &first.delta.content {
-                yield Ok(StreamPart::Content(content.clone()));
-              }
-              if let Some(calls) = &first.delta.tool_calls {
-                for call in calls {
-                  if let Some(function) = &call.function {
-                    tool_calls.entry(call.index)
-                      .and_modify(|partial| {
-                        if let Some(n) = &function.name {
-                          partial.name = format!("{}{}", partial.name, n);
-                        }
-                        if let Some(a) = &function.arguments.clone() {
-                          partial.json = format!("{}{}", partial.json, a);
-                        }
-                        if let Some(id) = &call.id {
-                          partial.id = id.clone();
-                        }
-                      })
-                      .or_insert_with(|| {
-                        let mut partial = ToolCal::default();
-                        if let Some(n) = function.name.clone() {
-                          partial.name = n;
-                        }
-                        if let Some(a) = function.arguments.clone() {
-                          partial.json = a;
-                        }
-                        if let Some(id) = &call.id {
-                          partial.id = id.clone();
-                        }
-                        // if let Some(id) = function.id
-                        partial
-                      });
-                  }
-                }
-              }
-              if let Some(FinishReason::ToolCalls) = first.finish_reason {
-                for call in tool_calls.into_values() {
-                  yield Ok(StreamPart::ToolCall(call));
-                }
-                tool_calls = HashMap::new();
-              }
-            },
-            Err(error) => yield Err(anyhow::Error::from(error))
-          }
+    pub async fn chat(&mut self) -> Result<()> {
+        let _ctrl_c = self.cancel_tools_on_ctrl_c();
+        print!("\x1B[2J\x1B[1;1H");
+        while let Some(user_input) = read_user_input().await? {
+            if let Err(err) = self.mcp.refresh(self.conversation.toolset_mut()).await {
+                eprintln!("could not refresh MCP tools: {err:#}");
+            }
+            let start = self.conversation.messages().len();
+            let stream = self.conversation.send_message(user_input);
+            stdout_stream(stream).await?;
+            println!();
+            self.record_since(start)?;
         }
-        })
+        Ok(())
     }
 
-    fn process_stream(&self, items: Vec<StreamPart>) -> Vec<ChatCompletionRequestMessage> {
-        let mut tool_calls = vec![];
-        let mut tool_responses = vec![];
-        let mut response = String::new();
-        for item in items {
-            match item {
-                StreamPart::ToolCall(call) => {
-                    if let Ok(context) = self
-                        .toolset
-                        .try_tool_call(NoContext(), &call.name, &call.json)
-                        .inspect_err(|err| eprintln!("error: {:?}", err))
-                    {
-                        tool_calls.push(ChatCompletionMessageToolCall {
-                            id: call.id.clone(),
-                            r#type: async_openai::types::ChatCompletionToolType::Function,
-                            function: FunctionCall {
-                                arguments: call.json,
-                                name: call.name,
-                            },
-                        });
-                        tool_responses.push(ChatCompletionRequestMessage::Tool(
-                            ChatCompletionRequestToolMessage {
-                              content: async_openai::types::ChatCompletionRequestToolMessageContent::Text(context),
-                              tool_call_id: call.id
-                            },
-                        ));
-                    }
-                }
-                StreamPart::Content(text) => response.push_str(text.as_str()),
-            };
+    /// Runs a single non-interactive turn.
+    ///
+    /// Anything piped into stdin is appended to `prompt` as context.
+    pub async fn run_once(
+        &mut self,
+        prompt: Option<String>,
+        piped: bool,
+        output: OutputFormat,
+    ) -> Result<()> {
+        let context = if piped {
+            Some(read_piped_input().await?).filter(|context| !context.trim().is_empty())
+        } else {
+            None
+        };
+        let message = match (prompt, context) {
+            (Some(prompt), Some(context)) => {
+                format!("{prompt}\n\n<stdin>\n{}\n</stdin>", context.trim_end())
+            }
+            (Some(prompt), None) => prompt,
+            (None, Some(context)) => context,
+            (None, None) => anyhow::bail!("no prompt given"),
+        };
+
+        let _ctrl_c = self.cancel_tools_on_ctrl_c();
+        let start = self.conversation.messages().len();
+        match output {
+            OutputFormat::Text => {
+                let result = pipeline_stream(self.conversation.send_message(message)).await;
+                self.record_since(start)?;
+                result
+            }
+            OutputFormat::Json => {
+                let error = drain_stream(self.conversation.send_message(message)).await;
+                self.record_since(start)?;
+                let messages = &self.conversation.messages()[start + 1..];
+                let content = messages.iter().rev().find_map(|message| match message {
+                    Message::Assistant {
+                        content: Some(content),
+                        ..
+                    } => Some(content.as_str()),
+                    _ => None,
+                });
+                let turn = TurnOutput {
+                    content,
+                    messages,
+                    error: error.as_ref().map(|error| format!("{error:#}")),
+                };
+                println!("{}", serde_json::to_string_pretty(&turn)?);
+                error.map_or(Ok(()), Err)
+            }
         }
-        let assistant_response =
-            ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
-                content: if response.is_empty() {
-                    None
-                } else {
-                    Some(ChatCompletionRequestAssistantMessageContent::Text(response))
-                },
-                tool_calls: if tool_calls.is_empty() {
-                    None
-                } else {
-                    Some(tool_calls)
-                },
-                ..Default::default()
-            });
-        let mut messages = vec![assistant_response];
-        messages.append(&mut tool_responses);
-        messages
     }
 }
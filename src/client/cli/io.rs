@@ -1,58 +1,121 @@
-use super::client::StreamPart;
+use crate::conversation::{ChatCompletionStream, StreamPart, ToolProgress, ToolResult};
 use anyhow::Result;
-use async_stream::stream;
-use futures::stream::{Stream, StreamExt};
-use rand::prelude::*;
-use std::pin::Pin;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::time::{Duration, sleep};
+use futures::stream::StreamExt;
+use std::sync::LazyLock;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines, Stdin};
+use tokio::sync::Mutex;
 
 const PROMPT: &str = ">>> ";
+const RED: &str = "\x1B[31m";
+const DIM: &str = "\x1B[2m";
+const RESET: &str = "\x1B[0m";
 
-pub async fn read_user_input() -> Result<String> {
+/// Failed and denied tool calls get a line of their own; successful results
+/// are only seen by the model.
+fn failed_tool_line(result: &ToolResult) -> Option<String> {
+    (!result.output.is_ok()).then(|| {
+        format!(
+            "[{} {}: {}]\n",
+            result.name, result.output.status, result.output.text
+        )
+    })
+}
+
+fn progress_line(progress: &ToolProgress) -> String {
+    format!("[{}: {}]\n", progress.name, progress.event)
+}
+
+/// Returns `None` once stdin is closed.
+pub async fn read_user_input() -> Result<Option<String>> {
     let mut output = tokio::io::stdout();
-    let input = tokio::io::stdin();
-    let mut reader = BufReader::new(input);
-    let mut buffer = String::new();
     output.write_all(PROMPT.as_bytes()).await?;
     output.flush().await?;
-    reader.read_line(&mut buffer).await?;
+    read_line().await
+}
+
+/// The lines of stdin, shared by the chat loop and the approver. A reader per
+/// call would drop whatever it buffered past its line, and `next_line` keeps
+/// a partly read line when a read is cancelled, e.g. by Ctrl-C.
+static STDIN_LINES: LazyLock<Mutex<Lines<BufReader<Stdin>>>> =
+    LazyLock::new(|| Mutex::new(BufReader::new(tokio::io::stdin()).lines()));
+
+/// Reads one line from stdin without its line ending, or `None` once stdin
+/// is closed.
+pub async fn read_line() -> Result<Option<String>> {
+    Ok(STDIN_LINES.lock().await.next_line().await?)
+}
+
+pub async fn read_piped_input() -> Result<String> {
+    let mut buffer = String::new();
+    tokio::io::stdin().read_to_string(&mut buffer).await?;
     Ok(buffer)
 }
 
-pub async fn stdout_stream(
-    mut stream: Pin<Box<dyn Stream<Item = Result<StreamPart>>>>,
-) -> Result<Vec<StreamPart>> {
+pub async fn stdout_stream(mut stream: ChatCompletionStream<'_>) -> Result<()> {
     let mut output = tokio::io::stdout();
-    let mut parts = Vec::new();
     while let Some(chunk) = stream.next().await {
         match chunk {
-            Ok(response) => {
-                if let StreamPart::Content(ref text) = response {
-                    output.write_all(text.as_bytes()).await?
+            Ok(StreamPart::Content(text)) => output.write_all(text.as_bytes()).await?,
+            Ok(StreamPart::ToolCall(call)) => {
+                let line = format!("\n[{}({})]\n", call.name, call.json);
+                output.write_all(line.as_bytes()).await?
+            }
+            Ok(StreamPart::ToolProgress(progress)) => {
+                let line = format!("{DIM}{}{RESET}", progress_line(&progress));
+                output.write_all(line.as_bytes()).await?
+            }
+            Ok(StreamPart::ToolResult(result)) => {
+                if let Some(line) = failed_tool_line(&result) {
+                    let line = format!("{RED}{line}{RESET}");
+                    output.write_all(line.as_bytes()).await?
                 }
-                parts.push(response);
             }
             Err(error) => output.write_all(format!("{}", error).as_bytes()).await?,
         }
         output.flush().await?;
     }
-    Ok(parts)
-}
-
-#[allow(unused)]
-pub fn dummy_ai_response() -> Pin<Box<dyn Stream<Item = String>>> {
-    let stream = stream! {
-    let mut rng = rand::rng();
-    let chunks = vec![
-        "thi", "s c", "ould", " be", " a real", " ai r", "esp", "ons", "e, but", " it's n", "ot",
-    ];
-        for chunk in chunks {
-            let millis = (rng.random::<f64>() * 250.0).floor() as u64;
-            let duration = Duration::from_millis(millis);
-            sleep(duration).await;
-            yield chunk.to_string();
+    Ok(())
+}
+
+/// Like [`stdout_stream`], but keeps stdout clean for pipelines: tool calls go
+/// to stderr and the first error aborts the turn.
+pub async fn pipeline_stream(mut stream: ChatCompletionStream<'_>) -> Result<()> {
+    let mut output = tokio::io::stdout();
+    let mut diagnostics = tokio::io::stderr();
+    while let Some(chunk) = stream.next().await {
+        match chunk? {
+            StreamPart::Content(text) => {
+                output.write_all(text.as_bytes()).await?;
+                output.flush().await?;
+            }
+            StreamPart::ToolCall(call) => {
+                let line = format!("[{}({})]\n", call.name, call.json);
+                diagnostics.write_all(line.as_bytes()).await?
+            }
+            StreamPart::ToolProgress(progress) => {
+                diagnostics
+                    .write_all(progress_line(&progress).as_bytes())
+                    .await?
+            }
+            StreamPart::ToolResult(result) => {
+                if let Some(line) = failed_tool_line(&result) {
+                    diagnostics.write_all(line.as_bytes()).await?
+                }
+            }
         }
-    };
-    Box::pin(stream)
+    }
+    output.write_all(b"\n").await?;
+    output.flush().await?;
+    Ok(())
+}
+
+/// Drains the stream without rendering it, returning the first error.
+pub async fn drain_stream(mut stream: ChatCompletionStream<'_>) -> Option<anyhow::Error> {
+    let mut first_error = None;
+    while let Some(chunk) = stream.next().await {
+        if let Err(error) = chunk {
+            first_error.get_or_insert(error);
+        }
+    }
+    first_error
 }
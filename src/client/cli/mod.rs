@@ -1,4 +1,6 @@
+mod approver;
 mod client;
 mod io;
 
+pub use approver::*;
 pub use client::*;
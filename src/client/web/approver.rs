@@ -0,0 +1,54 @@
+use crate::types::{Approval, ApprovalRequest, Approver};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::{mpsc, oneshot};
+
+/// A call waiting for the web user's decision. Dropping it denies the call.
+pub struct PendingApproval {
+    pub request: ApprovalRequest,
+    reply: oneshot::Sender<Approval>,
+}
+
+impl PendingApproval {
+    pub fn approve(self) {
+        let _ = self.reply.send(Approval::Approved);
+    }
+
+    pub fn deny(self, reason: impl Into<String>) {
+        let _ = self.reply.send(Approval::Denied(reason.into()));
+    }
+}
+
+/// Hands approval requests to the web app, e.g. to show a dialog, and waits
+/// for its answer.
+pub struct WebApprover {
+    requests: mpsc::UnboundedSender<PendingApproval>,
+}
+
+impl WebApprover {
+    /// The receiver yields a [`PendingApproval`] for every call to decide.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<PendingApproval>) {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        (Self { requests }, receiver)
+    }
+}
+
+#[async_trait]
+impl Approver for WebApprover {
+    async fn approve(&self, request: &ApprovalRequest) -> Result<Approval> {
+        let (reply, answer) = oneshot::channel();
+        let pending = PendingApproval {
+            request: request.clone(),
+            reply,
+        };
+        if self.requests.send(pending).is_err() {
+            return Ok(Approval::Denied(
+                "no one was there to approve the call".to_string(),
+            ));
+        }
+        Ok(answer
+            .await
+            .unwrap_or_else(|_| Approval::Denied("the approval request was dismissed".to_string())))
+    }
+}
@@ -1,3 +1,5 @@
+mod approver;
 mod client;
 
+pub use approver::*;
 pub use client::*;
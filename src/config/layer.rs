@@ -0,0 +1,245 @@
+use crate::provider::ProviderKind;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const USER_CONFIG_FILE: &str = "config.toml";
+pub const PROJECT_CONFIG_FILE: &str = ".hex.toml";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApprovalPolicy {
+    Ask,
+    #[default]
+    AutoApproveReads,
+    DenyAll,
+}
+
+impl FromStr for ApprovalPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ask" => Ok(ApprovalPolicy::Ask),
+            "auto-approve-reads" => Ok(ApprovalPolicy::AutoApproveReads),
+            "deny-all" => Ok(ApprovalPolicy::DenyAll),
+            other => Err(anyhow!(
+                "unknown approval policy '{other}' (expected ask, auto-approve-reads or deny-all)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ApprovalPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ApprovalPolicy::Ask => "ask",
+            ApprovalPolicy::AutoApproveReads => "auto-approve-reads",
+            ApprovalPolicy::DenyAll => "deny-all",
+        })
+    }
+}
+
+/// How to reach one MCP server: a `command` launched with stdio transport, or
+/// the `url` of a streamable HTTP endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct McpServerConfig {
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub url: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    /// Whether the server's tools count as mutating for approval. Unset, only
+    /// tools annotated `readOnlyHint` count as reads.
+    pub mutating: Option<bool>,
+}
+
+/// An executable that provides tools; see `plugins::Plugin` for the protocol.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Seconds a single invocation may run before it is killed.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Overrides the `mutating` flags in the manifest for approval.
+    #[serde(default)]
+    pub mutating: Option<bool>,
+}
+
+/// Where a resolved setting came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env,
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => f.write_str("default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env => f.write_str("environment"),
+            Source::Flag => f.write_str("command line"),
+        }
+    }
+}
+
+/// One layer of partial settings; unset fields defer to lower layers.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub provider: Option<ProviderKind>,
+    pub model: Option<String>,
+    pub api_base: Option<String>,
+    pub api_key: Option<String>,
+    /// Extra HTTP headers sent to the model API; layers add headers or
+    /// replace them by name.
+    pub headers: Option<BTreeMap<String, String>>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub tools: Option<Vec<String>>,
+    pub approval: Option<ApprovalPolicy>,
+    /// Path globs that tools may touch without asking.
+    pub allow_paths: Option<Vec<String>>,
+    pub system_prompt: Option<String>,
+    pub max_recursions: Option<u32>,
+    pub max_parallel_tools: Option<usize>,
+    /// Keyed by server name; layers add servers or replace them by name.
+    pub mcp_servers: Option<BTreeMap<String, McpServerConfig>>,
+    /// Keyed by plugin name, merged like `mcp_servers`.
+    pub plugins: Option<BTreeMap<String, PluginConfig>>,
+}
+
+impl ConfigLayer {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        Self::from_toml(&text).with_context(|| format!("invalid config in {}", path.display()))
+    }
+
+    /// Like `from_file`, for a `.hex.toml` that came with the project. A
+    /// cloned repository is not trusted with settings that run commands, send
+    /// the API key elsewhere or loosen approval, so those are rejected.
+    pub fn from_project_file(path: &Path) -> Result<Self> {
+        let layer = Self::from_file(path)?;
+        let untrusted = [
+            ("api_base", layer.api_base.is_some()),
+            ("approval", layer.approval.is_some()),
+            ("allow_paths", layer.allow_paths.is_some()),
+            ("mcp_servers", layer.mcp_servers.is_some()),
+            ("plugins", layer.plugins.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(key, is_set)| is_set.then_some(key))
+        .collect::<Vec<_>>();
+        if !untrusted.is_empty() {
+            bail!(
+                "{} may not set {}; move them to the user config, the environment or flags",
+                path.display(),
+                untrusted.join(", ")
+            );
+        }
+        Ok(layer)
+    }
+
+    /// Reads `HEX_<SETTING>` variables through `lookup`, e.g. `HEX_MODEL`.
+    /// `HEX_TOOLS` and `HEX_ALLOW_PATHS` are comma-separated lists, and
+    /// `HEX_HEADERS` a comma-separated list of `Name=value` pairs.
+    pub fn from_env(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| lookup(&format!("HEX_{name}")).filter(|value| !value.is_empty());
+        Ok(Self {
+            provider: var("PROVIDER").map(|value| value.parse()).transpose()?,
+            model: var("MODEL"),
+            api_base: var("API_BASE"),
+            api_key: var("API_KEY"),
+            headers: var("HEADERS")
+                .map(|value| {
+                    split_list(&value)
+                        .iter()
+                        .map(|header| parse_header(header))
+                        .collect::<Result<_>>()
+                })
+                .transpose()
+                .context("invalid HEX_HEADERS")?,
+            temperature: parse_env("HEX_TEMPERATURE", var("TEMPERATURE"))?,
+            max_tokens: parse_env("HEX_MAX_TOKENS", var("MAX_TOKENS"))?,
+            tools: var("TOOLS").map(|value| split_list(&value)),
+            approval: var("APPROVAL")
+                .map(|value| value.parse().context("invalid HEX_APPROVAL"))
+                .transpose()?,
+            allow_paths: var("ALLOW_PATHS").map(|value| split_list(&value)),
+            system_prompt: var("SYSTEM_PROMPT"),
+            max_recursions: parse_env("HEX_MAX_RECURSIONS", var("MAX_RECURSIONS"))?,
+            max_parallel_tools: parse_env("HEX_MAX_PARALLEL_TOOLS", var("MAX_PARALLEL_TOOLS"))?,
+            mcp_servers: None,
+            plugins: None,
+        })
+    }
+}
+
+/// Splits `Name=value`, as in `HEX_HEADERS` or `--header`.
+pub fn parse_header(header: &str) -> Result<(String, String)> {
+    let (name, value) = header
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected Name=value, got '{header}'"))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_env<T>(name: &str, value: Option<String>) -> Result<Option<T>>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .map(|value| value.parse().with_context(|| format!("invalid {name}")))
+        .transpose()
+}
+
+/// `$XDG_CONFIG_HOME/hex`, falling back to `~/.config/hex`.
+pub fn user_config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(base.join("hex"))
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join(USER_CONFIG_FILE))
+}
+
+/// The nearest `.hex.toml` in `dir` or one of its ancestors.
+pub fn project_config_path(dir: &Path) -> Option<PathBuf> {
+    find_upwards(dir, PROJECT_CONFIG_FILE)
+}
+
+pub(crate) fn find_upwards(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(file_name))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,7 @@
+mod layer;
+mod prompt;
+mod settings;
+
+pub use layer::*;
+pub use prompt::*;
+pub use settings::*;
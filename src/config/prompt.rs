@@ -0,0 +1,95 @@
+use super::layer::{find_upwards, user_config_dir};
+use super::settings::Config;
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const INSTRUCTIONS_FILE: &str = "HEX.md";
+
+pub const DEFAULT_SYSTEM_PROMPT: &str = "You are hex, a command-line assistant running in the \
+user's terminal. Use the available tools to inspect and change files in the current project \
+when that helps answer the request, and read files before editing them. Keep answers concise.";
+
+/// One part of the assembled system prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSection {
+    /// The built-in default, or the `system_prompt` setting that replaces it.
+    Base(String),
+    /// Instructions read from a `HEX.md` file.
+    Instructions { path: PathBuf, text: String },
+}
+
+/// The system prompt sent as the first message of every conversation.
+///
+/// It is assembled from the base prompt, the user's `~/.config/hex/HEX.md`
+/// and the nearest project `HEX.md` walking up from the working directory,
+/// in that order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemPrompt {
+    pub sections: Vec<PromptSection>,
+}
+
+impl SystemPrompt {
+    pub fn load(config: &Config) -> Result<Self> {
+        Self::assemble(
+            config.system_prompt.as_deref(),
+            user_config_dir().as_deref(),
+            &std::env::current_dir()?,
+        )
+    }
+
+    pub fn assemble(base: Option<&str>, user_dir: Option<&Path>, cwd: &Path) -> Result<Self> {
+        let base = match base {
+            Some(base) => base.to_string(),
+            None => format!(
+                "{DEFAULT_SYSTEM_PROMPT}\nThe current working directory is {}.",
+                cwd.display()
+            ),
+        };
+        let mut sections = vec![PromptSection::Base(base)];
+
+        let user_file = user_dir
+            .map(|dir| dir.join(INSTRUCTIONS_FILE))
+            .filter(|path| path.is_file());
+        let project_file = find_upwards(cwd, INSTRUCTIONS_FILE);
+        for path in [user_file, project_file].into_iter().flatten() {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("could not read {}", path.display()))?;
+            if !text.trim().is_empty() && !sections.iter().any(|section| section.is_from(&path)) {
+                sections.push(PromptSection::Instructions { path, text });
+            }
+        }
+        Ok(Self { sections })
+    }
+
+    pub fn text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl PromptSection {
+    fn is_from(&self, file: &Path) -> bool {
+        matches!(self, PromptSection::Instructions { path, .. } if path == file)
+    }
+}
+
+impl fmt::Display for SystemPrompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n\n")?;
+            }
+            match section {
+                PromptSection::Base(text) => f.write_str(text.trim_end())?,
+                PromptSection::Instructions { path, text } => write!(
+                    f,
+                    "# Instructions from {}\n\n{}",
+                    path.display(),
+                    text.trim_end()
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use super::layer::{
+    ApprovalPolicy, ConfigLayer, McpServerConfig, PluginConfig, Source, project_config_path,
+    user_config_path,
+};
+use crate::conversation::{MAX_PARALLEL_TOOLS, MAX_RECURSIONS};
+use crate::provider::{ProviderBuilder, ProviderKind};
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Fully resolved settings, with the source of every value.
+///
+/// Layers are applied lowest to highest: built-in defaults, the user config
+/// file, the project `.hex.toml`, `HEX_*` environment variables, then
+/// command-line flags.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub provider: ProviderKind,
+    pub model: Option<String>,
+    pub api_base: Option<String>,
+    pub api_key: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// `None` enables every built-in tool.
+    pub tools: Option<Vec<String>>,
+    pub approval: ApprovalPolicy,
+    pub allow_paths: Vec<String>,
+    pub system_prompt: Option<String>,
+    pub max_recursions: u32,
+    pub max_parallel_tools: usize,
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+    pub plugins: BTreeMap<String, PluginConfig>,
+    sources: BTreeMap<&'static str, Source>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            provider: ProviderKind::default(),
+            model: None,
+            api_base: None,
+            api_key: None,
+            headers: BTreeMap::new(),
+            temperature: None,
+            max_tokens: None,
+            tools: None,
+            approval: ApprovalPolicy::default(),
+            allow_paths: vec![],
+            system_prompt: None,
+            max_recursions: MAX_RECURSIONS,
+            max_parallel_tools: MAX_PARALLEL_TOOLS,
+            mcp_servers: BTreeMap::new(),
+            plugins: BTreeMap::new(),
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config files and environment, then applies `flags` on top.
+    pub fn load(flags: ConfigLayer) -> Result<Self> {
+        let mut layers = vec![];
+        if let Some(path) = user_config_path().filter(|path| path.is_file()) {
+            layers.push((Source::File(path.clone()), ConfigLayer::from_file(&path)?));
+        }
+        if let Some(path) = project_config_path(&std::env::current_dir()?) {
+            layers.push((
+                Source::File(path.clone()),
+                ConfigLayer::from_project_file(&path)?,
+            ));
+        }
+        layers.push((
+            Source::Env,
+            ConfigLayer::from_env(|name| std::env::var(name).ok())?,
+        ));
+        layers.push((Source::Flag, flags));
+        Ok(Self::resolve(layers))
+    }
+
+    pub fn resolve(layers: impl IntoIterator<Item = (Source, ConfigLayer)>) -> Self {
+        let mut config = Config::default();
+        for (source, layer) in layers {
+            let sources = &mut config.sources;
+            let mut merge = |key: &'static str, is_set: bool| {
+                if is_set {
+                    sources.insert(key, source.clone());
+                }
+                is_set
+            };
+            if merge("provider", layer.provider.is_some()) {
+                config.provider = layer.provider.unwrap_or_default();
+            }
+            if merge("model", layer.model.is_some()) {
+                config.model = layer.model;
+            }
+            if merge("api_base", layer.api_base.is_some()) {
+                config.api_base = layer.api_base;
+            }
+            if merge("api_key", layer.api_key.is_some()) {
+                config.api_key = layer.api_key;
+            }
+            if merge("headers", layer.headers.is_some()) {
+                config.headers.extend(layer.headers.unwrap_or_default());
+            }
+            if merge("temperature", layer.temperature.is_some()) {
+                config.temperature = layer.temperature;
+            }
+            if merge("max_tokens", layer.max_tokens.is_some()) {
+                config.max_tokens = layer.max_tokens;
+            }
+            if merge("tools", layer.tools.is_some()) {
+                config.tools = layer.tools;
+            }
+            if merge("approval", layer.approval.is_some()) {
+                config.approval = layer.approval.unwrap_or_default();
+            }
+            if merge("allow_paths", layer.allow_paths.is_some()) {
+                config.allow_paths = layer.allow_paths.unwrap_or_default();
+            }
+            if merge("system_prompt", layer.system_prompt.is_some()) {
+                config.system_prompt = layer.system_prompt;
+            }
+            if merge("max_recursions", layer.max_recursions.is_some()) {
+                config.max_recursions = layer.max_recursions.unwrap_or(MAX_RECURSIONS);
+            }
+            if merge("max_parallel_tools", layer.max_parallel_tools.is_some()) {
+                config.max_parallel_tools = layer.max_parallel_tools.unwrap_or(MAX_PARALLEL_TOOLS);
+            }
+            if merge("mcp_servers", layer.mcp_servers.is_some()) {
+                config
+                    .mcp_servers
+                    .extend(layer.mcp_servers.unwrap_or_default());
+            }
+            if merge("plugins", layer.plugins.is_some()) {
+                config.plugins.extend(layer.plugins.unwrap_or_default());
+            }
+        }
+        config
+    }
+
+    pub fn source(&self, key: &str) -> &Source {
+        self.sources.get(key).unwrap_or(&Source::Default)
+    }
+
+    pub fn provider_builder(&self) -> ProviderBuilder {
+        ProviderBuilder {
+            kind: self.provider,
+            api_base: self.api_base.clone(),
+            api_key: self.api_key.clone(),
+            model: self.model.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let unset = || "<unset>".to_string();
+        vec![
+            ("provider", self.provider.to_string()),
+            ("model", self.model.clone().unwrap_or_else(unset)),
+            ("api_base", self.api_base.clone().unwrap_or_else(unset)),
+            // secrets are only reported as set
+            (
+                "api_key",
+                self.api_key
+                    .as_ref()
+                    .map_or_else(unset, |_| "<set>".to_string()),
+            ),
+            ("headers", names(&self.headers)),
+            (
+                "temperature",
+                self.temperature.map_or_else(unset, |t| t.to_string()),
+            ),
+            (
+                "max_tokens",
+                self.max_tokens.map_or_else(unset, |t| t.to_string()),
+            ),
+            (
+                "tools",
+                self.tools
+                    .as_ref()
+                    .map_or_else(|| "<all>".to_string(), |tools| tools.join(",")),
+            ),
+            ("approval", self.approval.to_string()),
+            (
+                "allow_paths",
+                if self.allow_paths.is_empty() {
+                    "<none>".to_string()
+                } else {
+                    self.allow_paths.join(",")
+                },
+            ),
+            (
+                "system_prompt",
+                self.system_prompt
+                    .as_ref()
+                    .map_or_else(unset, |prompt| format!("{prompt:?}")),
+            ),
+            ("max_recursions", self.max_recursions.to_string()),
+            ("max_parallel_tools", self.max_parallel_tools.to_string()),
+            ("mcp_servers", names(&self.mcp_servers)),
+            ("plugins", names(&self.plugins)),
+        ]
+    }
+}
+
+fn names<T>(map: &BTreeMap<String, T>) -> String {
+    if map.is_empty() {
+        "<none>".to_string()
+    } else {
+        map.keys().cloned().collect::<Vec<_>>().join(",")
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in self.entries() {
+            writeln!(f, "{key:<18} = {value:<30} # {}", self.source(key))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+use super::stream::{ChatCompletionStream, StreamPart, ToolProgress, ToolResult};
+use crate::provider::{ChatDelta, ChatProvider, Message, ToolCall};
+use crate::types::{AsyncToolSet, CancellationToken, Progress, ToolCallError, ToolOutput};
+
+use anyhow::anyhow;
+use async_stream::stream;
+use futures::stream::{self, StreamExt};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+pub const MAX_RECURSIONS: u32 = 10;
+
+pub const MAX_PARALLEL_TOOLS: usize = 4;
+
+type ErrorRedactor = Box<dyn Fn(&anyhow::Error) -> String + Send + Sync>;
+
+/// Cancels the tool calls a [`Conversation`] is running, e.g. from a Ctrl-C
+/// handler. Cancelled calls are reported to the model as tool errors.
+#[derive(Clone, Default)]
+pub struct ToolCanceller {
+    running: Arc<Mutex<Option<CancellationToken>>>,
+}
+
+impl ToolCanceller {
+    /// Returns whether any tool calls were running.
+    pub fn cancel(&self) -> bool {
+        match self.running.lock().unwrap().take() {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+struct ProcessedStream {
+    pub is_tool_calls: bool,
+    pub new_messages: Vec<Message>,
+    pub tool_results: Vec<ToolResult>,
+}
+
+/// Owns the message history and drives the model/tool loop for a single chat.
+///
+/// Front ends feed user messages in and render the resulting event stream.
+pub struct Conversation<T: Clone> {
+    provider: Box<dyn ChatProvider>,
+    toolset: AsyncToolSet<T>,
+    messages: Vec<Message>,
+    context: T,
+    max_recursions: u32,
+    max_parallel_tools: usize,
+    redact_error: ErrorRedactor,
+    canceller: ToolCanceller,
+}
+
+impl<T: Clone> Conversation<T> {
+    pub fn new(provider: Box<dyn ChatProvider>, toolset: AsyncToolSet<T>, context: T) -> Self {
+        Conversation {
+            provider,
+            toolset,
+            messages: vec![],
+            context,
+            max_recursions: MAX_RECURSIONS,
+            max_parallel_tools: MAX_PARALLEL_TOOLS,
+            redact_error: Box::new(|err| format!("{err:#}")),
+            canceller: ToolCanceller::default(),
+        }
+    }
+
+    /// Maximum number of model requests made for one user message.
+    pub fn with_max_recursions(mut self, max_recursions: u32) -> Self {
+        self.max_recursions = max_recursions;
+        self
+    }
+
+    /// Maximum number of tool calls from one model response that run at
+    /// once. Tools marked sequential always run alone.
+    pub fn with_max_parallel_tools(mut self, max_parallel_tools: usize) -> Self {
+        self.max_parallel_tools = max_parallel_tools.max(1);
+        self
+    }
+
+    /// Controls how a failed tool's error is worded for the model. By default
+    /// the full context chain is sent so the model can correct itself.
+    pub fn with_error_redactor(
+        mut self,
+        redact: impl Fn(&anyhow::Error) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.redact_error = Box::new(redact);
+        self
+    }
+
+    /// Appends previously recorded messages, e.g. from a resumed session.
+    pub fn with_history(mut self, messages: impl IntoIterator<Item = Message>) -> Self {
+        self.messages.extend(messages);
+        self
+    }
+
+    /// Sets the leading system message, replacing any existing one.
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        let system = Message::System {
+            content: prompt.into(),
+        };
+        match self.messages.first_mut() {
+            Some(first @ Message::System { .. }) => *first = system,
+            _ => self.messages.insert(0, system),
+        }
+        self
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    pub fn tool_canceller(&self) -> ToolCanceller {
+        self.canceller.clone()
+    }
+
+    /// The tools offered to the model, e.g. to add or remove tools between turns.
+    pub fn toolset_mut(&mut self) -> &mut AsyncToolSet<T> {
+        &mut self.toolset
+    }
+
+    pub fn send_message(&mut self, message: String) -> ChatCompletionStream<'_> {
+        self.messages.push(Message::User { content: message });
+        self.make_chat_completion_stream()
+    }
+
+    fn make_chat_completion_stream(&mut self) -> ChatCompletionStream<'_> {
+        Box::pin(stream! {
+            for _ in 0..self.max_recursions {
+                let tools = self.toolset.tool_definitions();
+                let mut stream = match self.provider.stream_chat(&self.messages, &tools).await {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                };
+
+                let mut deltas = vec![];
+                while let Some(item) = stream.next().await {
+                    match item {
+                        Ok(delta) => {
+                            yield Ok(StreamPart::from(delta.clone()));
+                            deltas.push(delta);
+                        }
+                        Err(err) => {
+                            yield Err(err);
+                            break;
+                        }
+                    }
+                }
+                // call tools, passing on their progress, and aggregate the
+                // response to a new request
+                let (progress, mut updates) = mpsc::unbounded_channel();
+                let mut processed = {
+                    let processing = self.process_deltas(deltas, progress);
+                    tokio::pin!(processing);
+                    loop {
+                        let update = tokio::select! {
+                            processed = &mut processing => break processed,
+                            Some(update) = updates.recv() => update,
+                        };
+                        yield Ok(StreamPart::ToolProgress(update));
+                    }
+                };
+                while let Ok(update) = updates.try_recv() {
+                    yield Ok(StreamPart::ToolProgress(update));
+                }
+                self.messages.append(&mut processed.new_messages);
+                for result in processed.tool_results {
+                    yield Ok(StreamPart::ToolResult(result));
+                }
+                // if there are no tool calls, then done
+                if !processed.is_tool_calls {
+                    return;
+                }
+            }
+            yield Err(anyhow!(
+                "stopped after {} model requests without a final response",
+                self.max_recursions
+            ));
+        })
+    }
+
+    async fn process_deltas(
+        &self,
+        deltas: Vec<ChatDelta>,
+        progress: UnboundedSender<ToolProgress>,
+    ) -> ProcessedStream {
+        let mut tool_calls = vec![];
+        let mut response = String::new();
+        for delta in deltas {
+            match delta {
+                ChatDelta::ToolCall(call) => tool_calls.push(call),
+                ChatDelta::Content(text) => response.push_str(text.as_str()),
+            };
+        }
+
+        let outputs = self.run_tool_calls(&tool_calls, &progress).await;
+        let mut tool_responses = vec![];
+        let mut tool_results = vec![];
+        for (call, output) in tool_calls.iter().zip(outputs) {
+            tool_responses.push(Message::Tool {
+                tool_call_id: call.id.clone(),
+                content: output.model_content(),
+                status: output.status,
+            });
+            tool_results.push(ToolResult {
+                id: call.id.clone(),
+                name: call.name.clone(),
+                output,
+            });
+        }
+        let is_tool_calls = !tool_calls.is_empty();
+
+        let mut messages = vec![];
+        // an interrupted stream can leave nothing to record
+        if !response.is_empty() || !tool_calls.is_empty() {
+            messages.push(Message::Assistant {
+                content: if response.is_empty() {
+                    None
+                } else {
+                    Some(response)
+                },
+                tool_calls,
+            });
+        }
+        messages.append(&mut tool_responses);
+        ProcessedStream {
+            is_tool_calls,
+            new_messages: messages,
+            tool_results,
+        }
+    }
+
+    /// Runs up to `max_parallel_tools` calls at once. A sequential tool waits
+    /// for the calls before it and runs alone. Outputs are in call order.
+    async fn run_tool_calls(
+        &self,
+        calls: &[ToolCall],
+        progress: &UnboundedSender<ToolProgress>,
+    ) -> Vec<ToolOutput> {
+        if calls.is_empty() {
+            return vec![];
+        }
+        let cancel = CancellationToken::new();
+        *self.canceller.running.lock().unwrap() = Some(cancel.clone());
+        let mut outputs = Vec::with_capacity(calls.len());
+        let mut batch = vec![];
+        for call in calls {
+            if self.toolset.is_sequential(&call.name) {
+                outputs.extend(
+                    self.run_batch(std::mem::take(&mut batch), &cancel, progress)
+                        .await,
+                );
+                outputs.push(self.run_tool_call(call, &cancel, progress).await);
+            } else {
+                batch.push(call);
+            }
+        }
+        outputs.extend(self.run_batch(batch, &cancel, progress).await);
+        self.canceller.running.lock().unwrap().take();
+        outputs
+    }
+
+    async fn run_batch(
+        &self,
+        calls: Vec<&ToolCall>,
+        cancel: &CancellationToken,
+        progress: &UnboundedSender<ToolProgress>,
+    ) -> Vec<ToolOutput> {
+        // unlike `buffered`, a slow early call doesn't hold back later ones
+        let mut outputs = stream::iter(calls.into_iter().enumerate())
+            .map(|(index, call)| async move {
+                (index, self.run_tool_call(call, cancel, progress).await)
+            })
+            .buffer_unordered(self.max_parallel_tools)
+            .collect::<Vec<_>>()
+            .await;
+        outputs.sort_by_key(|(index, _)| *index);
+        outputs.into_iter().map(|(_, output)| output).collect()
+    }
+
+    async fn run_tool_call(
+        &self,
+        call: &ToolCall,
+        cancel: &CancellationToken,
+        progress: &UnboundedSender<ToolProgress>,
+    ) -> ToolOutput {
+        let progress = {
+            let (id, name, sender) = (call.id.clone(), call.name.clone(), progress.clone());
+            Progress::new(move |event| {
+                // the turn may already be over if the tool outlived its call
+                let _ = sender.send(ToolProgress {
+                    id: id.clone(),
+                    name: name.clone(),
+                    event,
+                });
+            })
+        };
+        match self
+            .toolset
+            .try_tool_call_with_progress(
+                self.context.clone(),
+                &call.name,
+                &call.json,
+                cancel.clone(),
+                progress,
+            )
+            .await
+        {
+            Ok(Ok(output)) => output,
+            Ok(Err(err)) => ToolOutput::error((self.redact_error)(&err)),
+            Err(err) => self.call_error_output(call, err),
+        }
+    }
+
+    /// Explains a call that never reached the tool, so the model can retry it.
+    fn call_error_output(&self, call: &ToolCall, err: ToolCallError) -> ToolOutput {
+        let mut output = err.to_output(&call.name, &call.json);
+        if let ToolCallError::NotFound(_) = err {
+            let names = self.toolset.tool_names();
+            let available = if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            };
+            output
+                .text
+                .push_str(&format!("; available tools: {available}"));
+        }
+        output
+    }
+}
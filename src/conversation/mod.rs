@@ -0,0 +1,5 @@
+mod engine;
+mod stream;
+
+pub use engine::*;
+pub use stream::*;
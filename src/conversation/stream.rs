@@ -0,0 +1,43 @@
+use crate::provider::{ChatDelta, ToolCall};
+use crate::types::{ProgressEvent, ToolOutput};
+
+use anyhow::Result;
+use futures::stream::Stream;
+use std::pin::Pin;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamPart {
+    Content(String),
+    ToolCall(ToolCall),
+    /// Emitted by streaming tools while they run; never sent to the model.
+    ToolProgress(ToolProgress),
+    /// Emitted once a tool call has run, before the next model request.
+    ToolResult(ToolResult),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolProgress {
+    /// The id of the tool call reporting progress.
+    pub id: String,
+    pub name: String,
+    pub event: ProgressEvent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolResult {
+    pub id: String,
+    pub name: String,
+    pub output: ToolOutput,
+}
+
+impl From<ChatDelta> for StreamPart {
+    fn from(value: ChatDelta) -> Self {
+        match value {
+            ChatDelta::Content(text) => StreamPart::Content(text),
+            ChatDelta::ToolCall(call) => StreamPart::ToolCall(call),
+        }
+    }
+}
+
+pub type ChatCompletionStream<'a> =
+    Pin<Box<dyn Stream<Item = Result<StreamPart, anyhow::Error>> + 'a>>;
@@ -1,2 +1,19 @@
+// lets `#[derive(HexTool)]` expansions refer to `::hex` inside this crate too
+extern crate self as hex;
+
+pub mod approval;
 pub mod client;
+pub mod config;
+pub mod conversation;
+pub mod mcp;
+pub mod offline_tools;
+pub mod plugins;
+pub mod provider;
+pub mod session;
 pub mod types;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use schemars;
+    pub use serde;
+}
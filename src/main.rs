@@ -1,15 +1,270 @@
-mod client;
-mod offline_tools;
-mod types;
+use hex::approval::{PolicyApprover, Unattended};
+use hex::client::cli::{CliApprover, CliClient, OutputFormat};
+use hex::config::{ApprovalPolicy, Config, ConfigLayer, SystemPrompt, parse_header};
+use hex::conversation::Conversation;
+use hex::mcp::{McpServer, McpServers};
+use hex::offline_tools::offline_toolset;
+use hex::plugins::register_plugins;
+use hex::provider::ProviderKind;
+use hex::session::{Session, sessions_dir};
+use hex::types::NoContext;
 
-use client::cli::CliClient;
-use offline_tools::offline_toolset;
-use types::Tool;
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, Subcommand};
+use std::io::IsTerminal;
+
+#[derive(Debug, Parser)]
+#[command(name = "hex", about = "A small CLI AI assistant with tool calling")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Run a single non-interactive turn with this prompt
+    prompt: Option<String>,
+
+    /// Same as the positional prompt; combine with piped stdin as context
+    #[arg(
+        short = 'p',
+        long = "prompt",
+        value_name = "PROMPT",
+        conflicts_with = "prompt"
+    )]
+    prompt_flag: Option<String>,
+
+    /// Output format for non-interactive turns
+    #[arg(long, value_enum, default_value_t)]
+    output: OutputFormat,
+
+    /// Only enable these tools, e.g. read_files,list_directory
+    #[arg(long, value_delimiter = ',', conflicts_with = "no_tools")]
+    tools: Option<Vec<String>>,
+
+    /// Disable all tools
+    #[arg(long)]
+    no_tools: bool,
+
+    /// When to ask before running a tool: ask, auto-approve-reads or deny-all
+    #[arg(long)]
+    approval: Option<ApprovalPolicy>,
+
+    /// Let tools touch paths matching these globs without asking, e.g. 'src/**'
+    #[arg(long, value_delimiter = ',')]
+    allow_paths: Option<Vec<String>>,
+
+    /// Resume a saved session by id
+    #[arg(long, value_name = "ID", conflicts_with = "continue_session")]
+    resume: Option<String>,
+
+    /// Resume the most recent session
+    #[arg(short = 'c', long = "continue")]
+    continue_session: bool,
+
+    /// Model backend (openai or anthropic)
+    #[arg(long, global = true)]
+    provider: Option<ProviderKind>,
+
+    /// Model name
+    #[arg(long, global = true)]
+    model: Option<String>,
+
+    /// Base URL of the model API, e.g. http://localhost:11434/v1
+    #[arg(long, global = true)]
+    api_base: Option<String>,
+
+    /// API key for the model backend, instead of OPENAI_API_KEY or ANTHROPIC_API_KEY
+    #[arg(long, global = true)]
+    api_key: Option<String>,
+
+    /// Extra HTTP header for the model API; repeatable
+    #[arg(long = "header", value_name = "NAME=VALUE", value_parser = parse_header, global = true)]
+    headers: Vec<(String, String)>,
+
+    /// Maximum model requests per user message
+    #[arg(long, global = true)]
+    max_recursions: Option<u32>,
+
+    /// Maximum tool calls run at once
+    #[arg(long, global = true)]
+    max_parallel_tools: Option<usize>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Inspect the resolved configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Browse saved session transcripts
+    Sessions {
+        #[command(subcommand)]
+        command: SessionsCommand,
+    },
+    /// Serve the built-in tools to MCP clients over stdio
+    McpServe,
+}
+
+#[derive(Debug, Subcommand)]
+enum SessionsCommand {
+    /// List saved sessions, oldest first
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print every resolved setting and where it came from
+    Show,
+    /// Print the assembled system prompt
+    Prompt,
+}
+
+impl Args {
+    fn config_layer(&self) -> ConfigLayer {
+        ConfigLayer {
+            provider: self.provider,
+            model: self.model.clone(),
+            api_base: self.api_base.clone(),
+            api_key: self.api_key.clone(),
+            headers: (!self.headers.is_empty()).then(|| self.headers.iter().cloned().collect()),
+            max_recursions: self.max_recursions,
+            max_parallel_tools: self.max_parallel_tools,
+            approval: self.approval,
+            allow_paths: self.allow_paths.clone(),
+            tools: if self.no_tools {
+                Some(vec![])
+            } else {
+                self.tools.clone()
+            },
+            ..Default::default()
+        }
+    }
+}
 
-use anyhow::Result;
 #[tokio::main]
 async fn main() -> Result<()> {
-    let mut cli_client = CliClient::new(offline_toolset());
-    cli_client.chat().await?;
+    let args = Args::parse();
+    let config = Config::load(args.config_layer())?;
+
+    match args.command {
+        Some(Command::Config {
+            command: ConfigCommand::Show,
+        }) => {
+            print!("{config}");
+            return Ok(());
+        }
+        Some(Command::Config {
+            command: ConfigCommand::Prompt,
+        }) => {
+            println!("{}", SystemPrompt::load(&config)?);
+            return Ok(());
+        }
+        Some(Command::Sessions {
+            command: SessionsCommand::List,
+        }) => return list_sessions(),
+        Some(Command::McpServe) => return mcp_serve(&config).await,
+        None => {}
+    }
+
+    let system_prompt = SystemPrompt::load(&config)?;
+    let mut toolset = offline_toolset();
+    if let Some(tools) = &config.tools {
+        let available = toolset.tool_names().join(", ");
+        toolset = toolset
+            .select(tools)
+            .with_context(|| format!("invalid tools setting (available: {available})"))?;
+    }
+    // `tools` selects built-ins only; `--no-tools` also skips MCP servers and plugins
+    let no_tools = config.tools.as_ref().is_some_and(Vec::is_empty);
+    let mut toolset = toolset.into_async();
+    if !no_tools {
+        toolset = register_plugins(&config.plugins, toolset).await?;
+    }
+    let mcp = if no_tools {
+        McpServers::default()
+    } else {
+        McpServers::connect(&config.mcp_servers).await?
+    };
+    let approver = PolicyApprover::new(config.approval, CliApprover::default())
+        .with_allowed_paths(&config.allow_paths)?;
+    let toolset = mcp.register(toolset).await?.with_approver(approver);
+    let conversation = Conversation::new(config.provider_builder().build()?, toolset, NoContext())
+        .with_max_recursions(config.max_recursions)
+        .with_max_parallel_tools(config.max_parallel_tools);
+
+    let dir = sessions_dir().ok_or_else(|| anyhow!("could not locate a data directory"))?;
+    let model = Some(config.provider_builder().resolved_model().to_string());
+    let resume = match (args.resume, args.continue_session) {
+        (Some(id), _) => Some(id),
+        (None, true) => Some(Session::latest(&dir)?.ok_or_else(|| anyhow!("no saved sessions"))?),
+        (None, false) => None,
+    };
+    let (session, conversation) = match resume {
+        Some(id) => {
+            let (session, history) = Session::resume(&dir, &id, model)?;
+            (session, conversation.with_history(history))
+        }
+        None => (Session::create(&dir, model)?, conversation),
+    };
+    let conversation = conversation.with_system_prompt(system_prompt.text());
+    let mut cli_client = CliClient::from_conversation(conversation)
+        .with_session(session)
+        .with_mcp(mcp);
+
+    let prompt = args.prompt.or(args.prompt_flag);
+    let piped = !std::io::stdin().is_terminal();
+    if prompt.is_some() || piped {
+        cli_client.run_once(prompt, piped, args.output).await
+    } else {
+        cli_client.chat().await?;
+        if let Some(session) = cli_client.session() {
+            eprintln!("session saved as {} (resume with --resume)", session.id());
+        }
+        Ok(())
+    }
+}
+
+/// Serves the built-ins. The terminal carries the protocol, so calls the
+/// policy and `allow_paths` don't settle are denied instead of asked about.
+async fn mcp_serve(config: &Config) -> Result<()> {
+    let mut toolset = offline_toolset();
+    if let Some(tools) = &config.tools {
+        toolset = toolset.select(tools)?;
+    }
+    let approver =
+        PolicyApprover::new(config.approval, Unattended).with_allowed_paths(&config.allow_paths)?;
+    McpServer::new(toolset.into_async().with_approver(approver), NoContext())
+        .serve(tokio::io::stdin(), tokio::io::stdout())
+        .await
+}
+
+fn list_sessions() -> Result<()> {
+    let Some(dir) = sessions_dir() else {
+        return Ok(());
+    };
+    for summary in Session::list(&dir)? {
+        let started = summary
+            .started
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let prompt = summary
+            .first_prompt
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let prompt = match prompt.char_indices().nth(60) {
+            Some((end, _)) => format!("{}...", &prompt[..end]),
+            None => prompt,
+        };
+        println!(
+            "{:<22} {:<16} {:>4}  {}",
+            summary.id, started, summary.messages, prompt
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,308 @@
+use super::protocol::{CallToolResult, ListToolsResult, McpTool, PROTOCOL_VERSION};
+use super::transport::{
+    HttpTransport, NotificationHandler, ProcessTransport, StreamTransport, Transport,
+};
+use crate::config::McpServerConfig;
+use crate::types::{AsyncToolSet, CancellationToken, DynamicTool, ToolInterrupted, ToolOutput};
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::RwLock;
+
+/// Separates the server name from the tool name in registered tool names.
+pub const TOOL_SEPARATOR: &str = "__";
+
+/// A session with one MCP server.
+pub struct McpClient {
+    name: String,
+    transport: Box<dyn Transport>,
+    tools_changed: Arc<AtomicBool>,
+    mutating: Option<bool>,
+    /// Counts restarts. Requests hold a read lock while they run and
+    /// restarts a write lock, so no request reaches a restarted server
+    /// before it is initialized.
+    generation: RwLock<u64>,
+}
+
+impl McpClient {
+    /// Starts the server's command or connects to its URL, and initializes
+    /// the session.
+    pub async fn connect(name: &str, config: &McpServerConfig) -> Result<Self> {
+        let tools_changed = Arc::new(AtomicBool::new(false));
+        let on_notification = tools_changed_handler(tools_changed.clone());
+        let transport: Box<dyn Transport> = match (&config.command, &config.url) {
+            (Some(command), None) => Box::new(ProcessTransport::spawn(
+                command,
+                &config.args,
+                &config.env,
+                on_notification,
+            )?),
+            (None, Some(url)) => {
+                Box::new(HttpTransport::new(url, &config.headers, on_notification)?)
+            }
+            _ => bail!("MCP server '{name}' needs exactly one of `command` or `url`"),
+        };
+        Ok(Self::start(name, transport, tools_changed)
+            .await?
+            .with_mutating(config.mutating))
+    }
+
+    /// Speaks newline-delimited JSON-RPC over the given streams, e.g. to a
+    /// server running in the same process.
+    pub async fn from_io(
+        name: &str,
+        reader: impl AsyncRead + Send + Unpin + 'static,
+        writer: impl AsyncWrite + Send + Unpin + 'static,
+    ) -> Result<Self> {
+        let tools_changed = Arc::new(AtomicBool::new(false));
+        let transport =
+            StreamTransport::new(reader, writer, tools_changed_handler(tools_changed.clone()));
+        Self::start(name, Box::new(transport), tools_changed).await
+    }
+
+    async fn start(
+        name: &str,
+        transport: Box<dyn Transport>,
+        tools_changed: Arc<AtomicBool>,
+    ) -> Result<Self> {
+        let client = Self {
+            name: name.to_string(),
+            transport,
+            tools_changed,
+            mutating: None,
+            generation: RwLock::new(0),
+        };
+        client
+            .initialize()
+            .await
+            .with_context(|| format!("could not initialize MCP server '{name}'"))?;
+        Ok(client)
+    }
+
+    async fn initialize(&self) -> Result<()> {
+        self.transport
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "hex", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        self.transport
+            .notify("notifications/initialized", json!({}))
+            .await
+    }
+
+    /// Sends a request, restarting the server once if it has gone away.
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let generation = self.generation.read().await;
+        let seen = *generation;
+        match self.transport.request(method, params.clone()).await {
+            Err(err) if self.transport.is_closed() => {
+                drop(generation);
+                self.restart(seen).await.with_context(|| {
+                    format!(
+                        "MCP server '{}' stopped ({err:#}) and could not be restarted",
+                        self.name
+                    )
+                })?;
+                let _generation = self.generation.read().await;
+                self.transport.request(method, params).await
+            }
+            result => result,
+        }
+    }
+
+    /// Restarts and initializes the server, unless a concurrent request did
+    /// so since it saw generation `seen`.
+    async fn restart(&self, seen: u64) -> Result<()> {
+        let mut generation = self.generation.write().await;
+        if *generation != seen {
+            return Ok(());
+        }
+        self.transport.restart().await?;
+        self.initialize().await?;
+        *generation += 1;
+        // a restarted server may offer different tools
+        self.tools_changed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Marks all of the server's tools as mutating or not, instead of
+    /// trusting their annotations.
+    pub fn with_mutating(mut self, mutating: Option<bool>) -> Self {
+        self.mutating = mutating;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_mutating(&self, tool: &McpTool) -> bool {
+        self.mutating.unwrap_or_else(|| tool.is_mutating())
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>> {
+        let mut tools = vec![];
+        let mut cursor = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let page: ListToolsResult =
+                serde_json::from_value(self.request("tools/list", params).await?)
+                    .context("invalid tools/list result")?;
+            tools.extend(page.tools);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<ToolOutput> {
+        self.call_tool_with_cancel(tool, arguments, &CancellationToken::new())
+            .await
+    }
+
+    /// Like `call_tool`, but gives up once `cancel` fires. Abandoned calls
+    /// are cancelled on the server too.
+    pub async fn call_tool_with_cancel(
+        &self,
+        tool: &str,
+        arguments: Value,
+        cancel: &CancellationToken,
+    ) -> Result<ToolOutput> {
+        let call = self.request(
+            "tools/call",
+            json!({ "name": tool, "arguments": arguments }),
+        );
+        let result = tokio::select! {
+            biased;
+            () = cancel.cancelled() => return Err(ToolInterrupted::Cancelled.into()),
+            result = call => result?,
+        };
+        let result: CallToolResult =
+            serde_json::from_value(result).context("invalid tools/call result")?;
+        Ok(result.into())
+    }
+
+    /// Whether the server's tools changed since the last call, either because
+    /// it said so or because it was restarted.
+    pub fn take_tools_changed(&self) -> bool {
+        self.tools_changed.swap(false, Ordering::SeqCst)
+    }
+}
+
+fn tools_changed_handler(tools_changed: Arc<AtomicBool>) -> NotificationHandler {
+    Arc::new(move |method| {
+        if method == "notifications/tools/list_changed" {
+            tools_changed.store(true, Ordering::SeqCst);
+        }
+    })
+}
+
+/// The configured MCP servers. Their tools are registered as
+/// `<server>__<tool>` so that servers cannot shadow each other or built-ins.
+#[derive(Default)]
+pub struct McpServers {
+    clients: Vec<Arc<McpClient>>,
+}
+
+impl McpServers {
+    pub async fn connect(configs: &BTreeMap<String, McpServerConfig>) -> Result<Self> {
+        let mut clients = vec![];
+        for (name, config) in configs {
+            let client = McpClient::connect(name, config)
+                .await
+                .with_context(|| format!("could not connect to MCP server '{name}'"))?;
+            clients.push(client);
+        }
+        Ok(Self::from_clients(clients))
+    }
+
+    pub fn from_clients(clients: impl IntoIterator<Item = McpClient>) -> Self {
+        Self {
+            clients: clients.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Adds every server's tools to `toolset`.
+    pub async fn register<C: Send + 'static>(
+        &self,
+        mut toolset: AsyncToolSet<C>,
+    ) -> Result<AsyncToolSet<C>> {
+        for client in &self.clients {
+            client.take_tools_changed();
+            insert_tools(client, &mut toolset, client.list_tools().await?)?;
+        }
+        Ok(toolset)
+    }
+
+    /// Re-lists the tools of servers that reported a change and replaces
+    /// their entries in `toolset`.
+    pub async fn refresh<C: Send + 'static>(&self, toolset: &mut AsyncToolSet<C>) -> Result<()> {
+        for client in &self.clients {
+            if !client.take_tools_changed() {
+                continue;
+            }
+            let tools = client.list_tools().await?;
+            let prefix = format!("{}{TOOL_SEPARATOR}", client.name());
+            let stale = toolset
+                .tool_names()
+                .into_iter()
+                .filter(|name| name.starts_with(&prefix))
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            for name in stale {
+                toolset.remove_tool(&name);
+            }
+            insert_tools(client, toolset, tools)?;
+        }
+        Ok(())
+    }
+}
+
+fn insert_tools<C: Send + 'static>(
+    client: &Arc<McpClient>,
+    toolset: &mut AsyncToolSet<C>,
+    tools: Vec<McpTool>,
+) -> Result<()> {
+    for tool in tools {
+        let name = format!("{}{TOOL_SEPARATOR}{}", client.name(), tool.name);
+        let mutating = client.is_mutating(&tool);
+        let remote_name = tool.name;
+        let client = client.clone();
+        let dynamic = DynamicTool::new(
+            name.clone(),
+            tool.description.unwrap_or_default(),
+            tool.input_schema,
+            move |arguments, _context, cancel| {
+                let client = client.clone();
+                let remote_name = remote_name.clone();
+                async move {
+                    client
+                        .call_tool_with_cancel(&remote_name, arguments, &cancel)
+                        .await
+                }
+            },
+        )
+        .with_mutating(mutating);
+        toolset
+            .insert_dynamic(dynamic)
+            .with_context(|| format!("could not register MCP tool '{name}'"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,7 @@
+mod client;
+pub mod protocol;
+mod server;
+mod transport;
+
+pub use client::*;
+pub use server::*;
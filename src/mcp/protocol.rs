@@ -0,0 +1,197 @@
+use crate::types::{ToolObject, ToolOutput, ToolStatus};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+pub const PARSE_ERROR: i64 = -32700;
+
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC 2.0 request, notification or response.
+///
+/// Requests carry `id` and `method`, notifications only `method`, and
+/// responses `id` with either `result` or `error`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RpcMessage {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcMessage {
+    pub fn request(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id.into()),
+            method: Some(method.to_string()),
+            params: Some(params),
+            ..Default::default()
+        }
+    }
+
+    pub fn notification(method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: Some(method.to_string()),
+            params: Some(params),
+            ..Default::default()
+        }
+    }
+
+    pub fn response(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            result: Some(result),
+            ..Default::default()
+        }
+    }
+
+    pub fn error_response(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            error: Some(RpcError {
+                code,
+                message: message.into(),
+                data: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.method.is_none() && self.id.is_some()
+    }
+}
+
+/// A tool as advertised in a `tools/list` result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub input_schema: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+impl McpTool {
+    /// Only a tool that declares `readOnlyHint` counts as a read; any other
+    /// tool may change something, even with `destructiveHint: false`.
+    pub fn is_mutating(&self) -> bool {
+        !self
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.read_only_hint)
+            .unwrap_or(false)
+    }
+}
+
+impl<T> From<&ToolObject<T>> for McpTool {
+    fn from(tool: &ToolObject<T>) -> Self {
+        Self {
+            name: tool.name.clone(),
+            description: Some(tool.description.clone()),
+            input_schema: tool.json_schema.clone(),
+            annotations: Some(ToolAnnotations {
+                read_only_hint: Some(!tool.mutating),
+                destructive_hint: None,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResult {
+    pub tools: Vec<McpTool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// The result of `tools/call`. Only text content is read back; other content
+/// types are summarized by type.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    #[serde(default)]
+    pub content: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl From<CallToolResult> for ToolOutput {
+    fn from(result: CallToolResult) -> Self {
+        let text = result
+            .content
+            .iter()
+            .map(
+                |content| match content.get("type").and_then(Value::as_str) {
+                    Some("text") => content["text"].as_str().unwrap_or_default().to_string(),
+                    Some(kind) => format!("[{kind} content]"),
+                    None => content.to_string(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+        let output = if result.is_error {
+            ToolOutput::error(text)
+        } else {
+            ToolOutput::ok(text)
+        };
+        match result.structured_content {
+            Some(data) => output.with_data(data),
+            None => output,
+        }
+    }
+}
+
+impl From<&ToolOutput> for CallToolResult {
+    fn from(output: &ToolOutput) -> Self {
+        // `isError` already marks errors; denials keep their prefix
+        let text = match (output.status, &output.data) {
+            (ToolStatus::Denied, _) => output.model_content(),
+            (_, Some(data)) if output.text.is_empty() => data.to_string(),
+            _ => output.text.clone(),
+        };
+        Self {
+            content: vec![serde_json::json!({ "type": "text", "text": text })],
+            // MCP only allows objects as structured content
+            structured_content: output.data.clone().filter(Value::is_object),
+            is_error: !output.is_ok(),
+        }
+    }
+}
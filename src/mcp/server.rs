@@ -0,0 +1,142 @@
+use super::protocol::{
+    CallToolResult, INVALID_PARAMS, ListToolsResult, METHOD_NOT_FOUND, McpTool, PARSE_ERROR,
+    PROTOCOL_VERSION, RpcMessage,
+};
+use super::transport::write_message;
+use crate::types::{AsyncToolSet, ToolCallError, ToolOutput};
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::sync::Mutex;
+
+#[derive(Deserialize)]
+struct CallToolParams {
+    name: String,
+    #[serde(default)]
+    arguments: Option<Value>,
+}
+
+/// Serves a toolset to MCP clients: `tools/list` lists every tool and
+/// `tools/call` runs one with a clone of `context`.
+pub struct McpServer<C> {
+    toolset: AsyncToolSet<C>,
+    context: C,
+    name: String,
+    version: String,
+}
+
+impl<C: Clone> McpServer<C> {
+    pub fn new(toolset: AsyncToolSet<C>, context: C) -> Self {
+        Self {
+            toolset,
+            context,
+            name: "hex".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// The name and version reported to clients in `initialize`.
+    pub fn with_server_info(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.name = name.into();
+        self.version = version.into();
+        self
+    }
+
+    /// Handles newline-delimited JSON-RPC from `reader` until it closes.
+    /// Requests are answered one at a time, in order.
+    pub async fn serve(
+        &self,
+        reader: impl AsyncRead + Unpin,
+        writer: impl AsyncWrite + Unpin,
+    ) -> Result<()> {
+        let writer = Mutex::new(writer);
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match serde_json::from_str::<RpcMessage>(&line) {
+                Ok(message) => self.handle(message).await,
+                Err(err) => Some(RpcMessage::error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    format!("invalid JSON-RPC message: {err}"),
+                )),
+            };
+            if let Some(reply) = reply {
+                write_message(&writer, &reply).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers one message; notifications and responses get no reply.
+    pub async fn handle(&self, message: RpcMessage) -> Option<RpcMessage> {
+        let (Some(id), Some(method)) = (message.id, message.method) else {
+            return None;
+        };
+        let params = message.params.unwrap_or(Value::Null);
+        let result = match method.as_str() {
+            "initialize" => json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": { "name": self.name, "version": self.version },
+            }),
+            "ping" => json!({}),
+            "tools/list" => json!(ListToolsResult {
+                tools: self
+                    .toolset
+                    .tools()
+                    .into_iter()
+                    .map(McpTool::from)
+                    .collect(),
+                next_cursor: None,
+            }),
+            "tools/call" => {
+                let params = match serde_json::from_value::<CallToolParams>(params) {
+                    Ok(params) => params,
+                    Err(err) => {
+                        return Some(RpcMessage::error_response(
+                            id,
+                            INVALID_PARAMS,
+                            format!("invalid tools/call params: {err}"),
+                        ));
+                    }
+                };
+                match self.call_tool(params).await {
+                    Ok(result) => json!(result),
+                    Err(message) => {
+                        return Some(RpcMessage::error_response(id, INVALID_PARAMS, message));
+                    }
+                }
+            }
+            _ => {
+                return Some(RpcMessage::error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    format!("method '{method}' is not supported"),
+                ));
+            }
+        };
+        Some(RpcMessage::response(id, result))
+    }
+
+    /// Runs a tool. Only an unknown tool is a protocol error; failures and
+    /// bad arguments are tool results so the calling model can react.
+    async fn call_tool(&self, params: CallToolParams) -> Result<CallToolResult, String> {
+        let arguments = params.arguments.unwrap_or_else(|| json!({})).to_string();
+        let output = match self
+            .toolset
+            .try_tool_call(self.context.clone(), &params.name, &arguments)
+            .await
+        {
+            Ok(Ok(output)) => output,
+            Ok(Err(err)) => ToolOutput::error(format!("{err:#}")),
+            Err(ToolCallError::NotFound(name)) => return Err(format!("unknown tool '{name}'")),
+            Err(err) => err.to_output(&params.name, &arguments),
+        };
+        Ok(CallToolResult::from(&output))
+    }
+}
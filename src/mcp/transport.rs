@@ -0,0 +1,482 @@
+use super::protocol::{METHOD_NOT_FOUND, RpcMessage};
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Lines of a stopped server's stderr quoted in errors.
+const STDERR_LINES: usize = 20;
+
+/// How long to wait for the rest of a stopped server's stderr.
+const STDERR_WAIT: Duration = Duration::from_millis(200);
+
+/// Called with the method of every notification the server sends.
+pub type NotificationHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn request(&self, method: &str, params: Value) -> Result<Value>;
+    async fn notify(&self, method: &str, params: Value) -> Result<()>;
+    /// Whether the server has gone away, so that a restart might help.
+    fn is_closed(&self) -> bool;
+    /// Reconnects to the server; the session must be initialized again.
+    async fn restart(&self) -> Result<()>;
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<RpcMessage>>>>;
+type Writer = Arc<tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+
+/// Newline-delimited JSON-RPC over a pair of byte streams.
+pub struct StreamTransport {
+    writer: Writer,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    reader: JoinHandle<()>,
+}
+
+impl StreamTransport {
+    pub fn new(
+        reader: impl AsyncRead + Send + Unpin + 'static,
+        writer: impl AsyncWrite + Send + Unpin + 'static,
+        on_notification: NotificationHandler,
+    ) -> Self {
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(Box::new(writer)));
+        let pending = Pending::default();
+        let closed = Arc::new(AtomicBool::new(false));
+        let reader = tokio::spawn(read_messages(
+            BufReader::new(reader),
+            writer.clone(),
+            pending.clone(),
+            closed.clone(),
+            on_notification,
+        ));
+        Self {
+            writer,
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+            reader,
+        }
+    }
+}
+
+impl Drop for StreamTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+async fn read_messages(
+    reader: impl AsyncBufReadExt + Unpin,
+    writer: Writer,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+    on_notification: NotificationHandler,
+) {
+    let mut lines = reader.lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        // servers sometimes log to stdout; skip anything that isn't JSON-RPC
+        let Ok(message) = serde_json::from_str::<RpcMessage>(&line) else {
+            continue;
+        };
+        if message.is_response() {
+            let id = message.id.as_ref().and_then(Value::as_u64);
+            let sender = id.and_then(|id| pending.lock().unwrap().remove(&id));
+            if let Some(sender) = sender {
+                let _ = sender.send(message);
+            }
+        } else if let Some(method) = &message.method {
+            match message.id {
+                Some(id) => {
+                    let reply = if method == "ping" {
+                        RpcMessage::response(id, json!({}))
+                    } else {
+                        RpcMessage::error_response(
+                            id,
+                            METHOD_NOT_FOUND,
+                            format!("method '{method}' is not supported"),
+                        )
+                    };
+                    let _ = write_message(&writer, &reply).await;
+                }
+                None => on_notification(method),
+            }
+        }
+    }
+    closed.store(true, Ordering::SeqCst);
+    // dropping the senders fails every request still waiting
+    pending.lock().unwrap().clear();
+}
+
+pub(crate) async fn write_message(
+    writer: &tokio::sync::Mutex<impl AsyncWrite + Unpin + ?Sized>,
+    message: &RpcMessage,
+) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    let mut writer = writer.lock().await;
+    writer.write_all(&line).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Runs a function when dropped, e.g. when a request's future is abandoned.
+struct OnDrop<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Drop for OnDrop<F> {
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.0.take() {
+            on_drop();
+        }
+    }
+}
+
+fn cancelled_notification(id: u64) -> RpcMessage {
+    RpcMessage::notification(
+        "notifications/cancelled",
+        json!({ "requestId": id, "reason": "the call was cancelled" }),
+    )
+}
+
+fn into_result(method: &str, response: RpcMessage) -> Result<Value> {
+    match response.error {
+        Some(error) => bail!("{method} failed: {} (code {})", error.message, error.code),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
+
+#[async_trait]
+impl Transport for StreamTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let closed = || anyhow!("the server closed the connection");
+        if self.is_closed() {
+            return Err(closed());
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+        // the reader may have finished between the check above and the insert
+        if self.is_closed() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(closed());
+        }
+        // a request that is still pending when dropped was cancelled or timed
+        // out, so the server can stop working on it
+        let _cancel_on_drop = OnDrop(Some(|| {
+            let abandoned = self.pending.lock().unwrap().remove(&id).is_some();
+            if abandoned && !self.is_closed() {
+                let writer = self.writer.clone();
+                tokio::spawn(async move {
+                    let _ = write_message(&writer, &cancelled_notification(id)).await;
+                });
+            }
+        }));
+        let request = RpcMessage::request(id, method, params);
+        if let Err(err) = write_message(&self.writer, &request).await {
+            self.pending.lock().unwrap().remove(&id);
+            self.closed.store(true, Ordering::SeqCst);
+            return Err(err.context("could not write to the server"));
+        }
+        let response = receiver.await.map_err(|_| closed())?;
+        into_result(method, response)
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        write_message(&self.writer, &RpcMessage::notification(method, params)).await
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn restart(&self) -> Result<()> {
+        bail!("this connection cannot be restarted")
+    }
+}
+
+/// A server launched as a child process and spoken to over its stdin/stdout.
+/// `restart` launches it again.
+pub struct ProcessTransport {
+    command: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    on_notification: NotificationHandler,
+    child: Mutex<Option<Child>>,
+    connection: Mutex<Option<(Arc<StreamTransport>, StderrTail)>>,
+}
+
+impl ProcessTransport {
+    pub fn spawn(
+        command: &str,
+        args: &[String],
+        env: &BTreeMap<String, String>,
+        on_notification: NotificationHandler,
+    ) -> Result<Self> {
+        let transport = Self {
+            command: command.to_string(),
+            args: args.to_vec(),
+            env: env.clone(),
+            on_notification,
+            child: Mutex::new(None),
+            connection: Mutex::new(None),
+        };
+        transport.launch()?;
+        Ok(transport)
+    }
+
+    fn launch(&self) -> Result<()> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("could not start `{}`", self.command))?;
+        let stdin = child.stdin.take().context("missing stdin pipe")?;
+        let stdout = child.stdout.take().context("missing stdout pipe")?;
+        let stderr = StderrTail::capture(child.stderr.take().context("missing stderr pipe")?);
+        let connection = StreamTransport::new(stdout, stdin, self.on_notification.clone());
+        // replacing the old child kills it
+        *self.child.lock().unwrap() = Some(child);
+        *self.connection.lock().unwrap() = Some((Arc::new(connection), stderr));
+        Ok(())
+    }
+
+    fn connection(&self) -> Result<(Arc<StreamTransport>, StderrTail)> {
+        self.connection
+            .lock()
+            .unwrap()
+            .clone()
+            .context("the server is not running")
+    }
+}
+
+/// The last lines a server wrote to stderr, quoted when it stops.
+#[derive(Clone, Default)]
+struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    finished: CancellationToken,
+}
+
+impl StderrTail {
+    fn capture(stderr: ChildStderr) -> Self {
+        let tail = Self::default();
+        let capturing = tail.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let mut kept = capturing.lines.lock().unwrap();
+                if kept.len() == STDERR_LINES {
+                    kept.pop_front();
+                }
+                kept.push_back(line);
+            }
+            capturing.finished.cancel();
+        });
+        tail
+    }
+
+    /// Adds the end of stderr to `err`, once the server has had a moment to
+    /// finish writing it.
+    async fn explain(&self, err: anyhow::Error) -> anyhow::Error {
+        let _ = tokio::time::timeout(STDERR_WAIT, self.finished.cancelled()).await;
+        let lines = self.lines.lock().unwrap();
+        if lines.is_empty() {
+            return err;
+        }
+        let tail = lines.iter().map(String::as_str).collect::<Vec<_>>();
+        anyhow!("{err:#}; stderr:\n{}", tail.join("\n"))
+    }
+}
+
+#[async_trait]
+impl Transport for ProcessTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let (connection, stderr) = self.connection()?;
+        match connection.request(method, params).await {
+            Err(err) if connection.is_closed() => Err(stderr.explain(err).await),
+            result => result,
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.connection()?.0.notify(method, params).await
+    }
+
+    fn is_closed(&self) -> bool {
+        self.connection()
+            .map_or(true, |(connection, _)| connection.is_closed())
+    }
+
+    async fn restart(&self) -> Result<()> {
+        self.launch()
+    }
+}
+
+/// MCP streamable HTTP: every message is POSTed, and the reply is either a
+/// JSON body or an event stream ending with the response.
+pub struct HttpTransport {
+    http: reqwest::Client,
+    url: String,
+    session: Mutex<Option<String>>,
+    next_id: AtomicU64,
+    closed: AtomicBool,
+    on_notification: NotificationHandler,
+}
+
+impl HttpTransport {
+    pub fn new(
+        url: &str,
+        headers: &BTreeMap<String, String>,
+        on_notification: NotificationHandler,
+    ) -> Result<Self> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("invalid header name {name:?}"))?,
+                HeaderValue::from_str(value)
+                    .with_context(|| format!("invalid value for header {name:?}"))?,
+            );
+        }
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .default_headers(header_map)
+                .build()?,
+            url: url.to_string(),
+            session: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+            closed: AtomicBool::new(false),
+            on_notification,
+        })
+    }
+
+    async fn post(&self, message: &RpcMessage) -> Result<reqwest::Response> {
+        let session = self.session.lock().unwrap().clone();
+        let mut request = self
+            .http
+            .post(&self.url)
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(session) = &session {
+            request = request.header(SESSION_HEADER, session);
+        }
+        let response = request.send().await?;
+        if session.is_some() && response.status() == reqwest::StatusCode::NOT_FOUND {
+            self.closed.store(true, Ordering::SeqCst);
+            bail!("the server ended the session");
+        }
+        let response = response.error_for_status()?;
+        if let Some(session) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            *self.session.lock().unwrap() = Some(session.to_string());
+        }
+        Ok(response)
+    }
+
+    /// Tells the server to stop working on request `id` without waiting for
+    /// the notification to be sent.
+    fn cancel_later(&self, id: u64) {
+        if self.is_closed() {
+            return;
+        }
+        let mut request = self
+            .http
+            .post(&self.url)
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(&cancelled_notification(id));
+        if let Some(session) = self.session.lock().unwrap().as_deref() {
+            request = request.header(SESSION_HEADER, session);
+        }
+        tokio::spawn(async move {
+            let _ = request.send().await;
+        });
+    }
+
+    /// Reads server-sent events until the response to `id` arrives, passing
+    /// on any notifications sent before it.
+    async fn read_event_stream(&self, response: reqwest::Response, id: u64) -> Result<RpcMessage> {
+        let mut stream = response.bytes_stream();
+        let mut buffer = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            buffer.extend(chunk?.iter().filter(|byte| **byte != b'\r'));
+            while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+                let event = String::from_utf8_lossy(&buffer[..end]).to_string();
+                buffer.drain(..end + 2);
+                let data = event
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .map(str::trim_start)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let Ok(message) = serde_json::from_str::<RpcMessage>(&data) else {
+                    continue;
+                };
+                if message.is_response() && message.id == Some(id.into()) {
+                    return Ok(message);
+                }
+                if let (Some(method), None) = (&message.method, &message.id) {
+                    (self.on_notification)(method);
+                }
+            }
+        }
+        bail!("the event stream ended without a response")
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut cancel_on_drop = OnDrop(Some(|| self.cancel_later(id)));
+        let response = self.post(&RpcMessage::request(id, method, params)).await?;
+        let is_event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        let message = if is_event_stream {
+            self.read_event_stream(response, id).await?
+        } else {
+            response.json::<RpcMessage>().await?
+        };
+        cancel_on_drop.0 = None;
+        into_result(method, message)
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.post(&RpcMessage::notification(method, params))
+            .await
+            .map(drop)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn restart(&self) -> Result<()> {
+        *self.session.lock().unwrap() = None;
+        self.closed.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
@@ -1,30 +1,28 @@
-use crate::Tool;
 use crate::types::NoContext;
-use schemars::JsonSchema;
-use serde::Deserialize;
+use crate::types::{HexTool, Tool, ToolOutput};
+use anyhow::{Context, Result};
+use serde_json::json;
 
-#[derive(Debug, Deserialize, JsonSchema, Default)]
-#[schemars(description = "list the files in a directory")]
+/// list the files in a directory
+#[derive(HexTool, Debug, Default)]
+#[tool(name = "list_directory")]
 pub struct ListDirectory {
-    #[schemars(description = "file path to list")]
+    /// file path to list
     pub path: String,
 }
 
 impl Tool for ListDirectory {
     type Context = NoContext;
-    fn apply(&self, _: Self::Context) -> String {
-        let contents = std::fs::read_dir(&self.path);
-        if contents.is_err() {
-            return format!("could not list {}", self.path);
-        }
-        let contents = contents.unwrap();
-        contents
+    fn apply(&self, _: Self::Context) -> Result<ToolOutput> {
+        let contents = std::fs::read_dir(&self.path)
+            .with_context(|| format!("could not list {}", self.path))?;
+        let entries = contents
             .into_iter()
             .filter_map(|path| match path {
                 Ok(entry) => entry.path().to_str().map(str::to_string),
                 Err(_) => None,
             })
-            .collect::<Vec<_>>()
-            .join("\n")
+            .collect::<Vec<_>>();
+        Ok(ToolOutput::ok(entries.join("\n")).with_data(json!(entries)))
     }
 }
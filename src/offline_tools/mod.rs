@@ -21,4 +21,9 @@ pub fn offline_toolset() -> SyncToolSet<NoContext> {
         .expect("edit file")
         .add_tool::<CreateFile>()
         .expect("create file")
+        // approvers are asked before these run, one at a time
+        .mutating(&["edit_file", "create_file"])
+        .expect("mutating tools")
+        .sequential(&["edit_file", "create_file"])
+        .expect("mutating tools")
 }
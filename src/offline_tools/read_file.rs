@@ -1,25 +1,38 @@
-use crate::{Tool, types::NoContext};
-use schemars::JsonSchema;
-use serde::Deserialize;
+use crate::types::{HexTool, NoContext, Tool, ToolOutput, sync_tool_cancelled};
+use anyhow::Result;
 use std::fs::read_to_string;
 
-#[derive(Deserialize, JsonSchema, Debug, Default)]
-#[schemars(description = "Read one or more files and add their contents to context")]
+/// Read one or more files and add their contents to context
+#[derive(HexTool, Debug, Default)]
+#[tool(name = "read_files")]
 pub struct ReadFiles {
-    #[schemars(description = "a list of relative file paths to read")]
+    /// a list of relative file paths to read
     pub paths: Vec<String>,
 }
 
 impl Tool for ReadFiles {
     type Context = NoContext;
-    fn apply(&self, _: Self::Context) -> String {
-        self.paths
+    fn apply(&self, _: Self::Context) -> Result<ToolOutput> {
+        let mut failed = false;
+        let text = self
+            .paths
             .iter()
+            // nobody is waiting for the rest
+            .take_while(|_| !sync_tool_cancelled())
             .map(|path| match read_to_string(path) {
                 Ok(content) => format!("[{path}]\n{content}"),
-                Err(_) => "<failed to read file>".to_string(),
+                Err(err) => {
+                    failed = true;
+                    format!("[{path}]\n<failed to read file: {err}>")
+                }
             })
             .collect::<Vec<_>>()
-            .join("\n")
+            .join("\n");
+        // partial reads still carry the files that could be read
+        Ok(if failed {
+            ToolOutput::error(text)
+        } else {
+            ToolOutput::ok(text)
+        })
     }
 }
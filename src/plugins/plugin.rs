@@ -0,0 +1,225 @@
+use crate::config::PluginConfig;
+use crate::mcp::TOOL_SEPARATOR;
+use crate::types::{
+    AsyncToolSet, CancellationToken, DynamicTool, ToolInterrupted, ToolOutput, ToolStatus,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::process::{Output, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+pub const DEFAULT_PLUGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest stderr excerpt quoted in errors.
+const STDERR_LIMIT: usize = 2000;
+
+/// The tools a plugin provides, printed by `<command> manifest`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Manifest {
+    pub tools: Vec<ManifestTool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ManifestTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    /// Whether the tool changes anything, for approval. Unset counts as
+    /// mutating.
+    #[serde(default)]
+    pub mutating: Option<bool>,
+}
+
+/// The result printed by `<command> call <tool>`. Everything is optional, so
+/// `{"text": "done"}` is a successful result.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CallResult {
+    status: ToolStatus,
+    text: String,
+    data: Option<Value>,
+}
+
+/// An executable providing tools over a JSON stdio protocol:
+///
+/// - `<command> [args] manifest` prints a [`Manifest`].
+/// - `<command> [args] call <tool>` reads the arguments object on stdin and
+///   prints `{"status": "ok" | "error" | "denied", "text": ..., "data": ...}`.
+///
+/// Each invocation is a fresh process that is killed when it exceeds the
+/// timeout. A non-zero exit fails the call with the end of stderr; after a
+/// successful call, stderr is attached to the output's data as `stderr`.
+pub struct Plugin {
+    name: String,
+    config: PluginConfig,
+}
+
+impl Plugin {
+    pub fn new(name: &str, config: PluginConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn timeout(&self) -> Duration {
+        self.config
+            .timeout_secs
+            .map_or(DEFAULT_PLUGIN_TIMEOUT, Duration::from_secs)
+    }
+
+    pub async fn manifest(&self) -> Result<Manifest> {
+        let output = self
+            .run(&["manifest"], None, &CancellationToken::new())
+            .await?;
+        serde_json::from_slice(&output.stdout).context("invalid manifest")
+    }
+
+    /// Runs `tool`, killing the plugin if `cancel` fires first.
+    pub async fn call(
+        &self,
+        tool: &str,
+        arguments: &Value,
+        cancel: &CancellationToken,
+    ) -> Result<ToolOutput> {
+        let output = self
+            .run(
+                &["call", tool],
+                Some(serde_json::to_vec(arguments)?),
+                cancel,
+            )
+            .await?;
+        let result: CallResult = serde_json::from_slice(&output.stdout).with_context(|| {
+            format!(
+                "invalid result: {}",
+                String::from_utf8_lossy(&output.stdout).trim()
+            )
+        })?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let data = match (result.data, stderr_tail(stderr.trim())) {
+            (data, stderr) if stderr.is_empty() => data,
+            (Some(Value::Object(mut data)), stderr) => {
+                data.insert("stderr".to_string(), stderr.into());
+                Some(Value::Object(data))
+            }
+            (None, stderr) => Some(json!({ "stderr": stderr })),
+            (Some(data), stderr) => Some(json!({ "result": data, "stderr": stderr })),
+        };
+        Ok(ToolOutput {
+            status: result.status,
+            text: result.text,
+            data,
+        })
+    }
+
+    /// Runs the plugin with `action` appended to its arguments and returns
+    /// its output once it exits successfully.
+    async fn run(
+        &self,
+        action: &[&str],
+        input: Option<Vec<u8>>,
+        cancel: &CancellationToken,
+    ) -> Result<Output> {
+        let mut child = Command::new(&self.config.command)
+            .args(&self.config.args)
+            .args(action)
+            .envs(&self.config.env)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("could not start `{}`", self.config.command))?;
+
+        let stdin = child.stdin.take();
+        let write_input = async move {
+            if let (Some(mut stdin), Some(input)) = (stdin, input) {
+                // a plugin that exits without reading its input is not an error
+                let _ = stdin.write_all(&input).await;
+            }
+        };
+        let timeout = self.timeout();
+        // dropping the child on timeout or cancellation kills it
+        let run = tokio::time::timeout(timeout, async {
+            tokio::join!(write_input, child.wait_with_output())
+        });
+        let (_, output) = tokio::select! {
+            biased;
+            () = cancel.cancelled() => return Err(ToolInterrupted::Cancelled.into()),
+            result = run => {
+                result.with_context(|| format!("timed out after {}s", timeout.as_secs()))?
+            }
+        };
+        let output = output?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            match stderr_tail(stderr.trim()) {
+                tail if tail.is_empty() => bail!("exited with {}", output.status),
+                tail => bail!("exited with {}: {tail}", output.status),
+            }
+        }
+        Ok(output)
+    }
+}
+
+fn stderr_tail(stderr: &str) -> String {
+    match stderr.char_indices().rev().nth(STDERR_LIMIT - 1) {
+        Some((start, _)) if start > 0 => format!("...{}", &stderr[start..]),
+        _ => stderr.to_string(),
+    }
+}
+
+/// Asks every plugin for its manifest and adds its tools to `toolset` as
+/// `<plugin>__<tool>`, like MCP tools.
+pub async fn register_plugins<C: Send + 'static>(
+    plugins: &BTreeMap<String, PluginConfig>,
+    mut toolset: AsyncToolSet<C>,
+) -> Result<AsyncToolSet<C>> {
+    for (name, config) in plugins {
+        let plugin = Arc::new(Plugin::new(name, config.clone()));
+        let manifest = plugin
+            .manifest()
+            .await
+            .with_context(|| format!("could not load plugin '{name}'"))?;
+        for tool in manifest.tools {
+            let tool_name = tool.name.clone();
+            let mutating = config.mutating.or(tool.mutating).unwrap_or(true);
+            let plugin = plugin.clone();
+            let dynamic = DynamicTool::new(
+                format!("{name}{TOOL_SEPARATOR}{}", tool.name),
+                tool.description,
+                tool.input_schema,
+                move |arguments, _context, cancel| {
+                    let plugin = plugin.clone();
+                    let tool_name = tool_name.clone();
+                    async move {
+                        plugin
+                            .call(&tool_name, &arguments, &cancel)
+                            .await
+                            .with_context(|| format!("plugin '{}' failed", plugin.name()))
+                    }
+                },
+            )
+            .with_mutating(mutating);
+            toolset
+                .insert_dynamic(dynamic)
+                .with_context(|| format!("could not register tools of plugin '{name}'"))?;
+        }
+    }
+    Ok(toolset)
+}
@@ -0,0 +1,303 @@
+use super::builder::ProviderBuilder;
+use super::chat_provider::ChatProvider;
+use super::types::{ChatDelta, Message, ProviderStream, ToolCall, ToolDefinition};
+use crate::types::ToolStatus;
+
+use anyhow::{Result, anyhow};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use reqwest_eventsource::{Error as EventSourceError, Event, RequestBuilderExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+const API_BASE: &str = "https://api.anthropic.com/v1";
+const API_VERSION: &str = "2023-06-01";
+pub(crate) const DEFAULT_MODEL: &str = "claude-sonnet-4-20250514";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Native Anthropic Messages API backend.
+pub struct AnthropicProvider {
+    http: reqwest::Client,
+    api_base: String,
+    api_key: String,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: u32,
+}
+
+impl AnthropicProvider {
+    /// Reads the key from `ANTHROPIC_API_KEY`.
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_base: API_BASE.to_string(),
+            api_key: std::env::var("ANTHROPIC_API_KEY").unwrap_or_default(),
+            model: DEFAULT_MODEL.to_string(),
+            temperature: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+        }
+    }
+
+    /// Unset values fall back to [`AnthropicProvider::new`].
+    pub fn from_builder(builder: &ProviderBuilder) -> Result<Self> {
+        let defaults = Self::new();
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .default_headers(builder.header_map()?)
+                .build()?,
+            api_base: builder.api_base.clone().unwrap_or(defaults.api_base),
+            api_key: builder.api_key.clone().unwrap_or(defaults.api_key),
+            model: builder.model.clone().unwrap_or(defaults.model),
+            temperature: builder.temperature,
+            max_tokens: builder.max_tokens.unwrap_or(defaults.max_tokens),
+        })
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    fn request_body(&self, messages: &[Message], tools: &[ToolDefinition]) -> Value {
+        let system = messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::System { content } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": self.max_tokens,
+            "messages": anthropic_messages(messages),
+            "stream": true,
+        });
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if !system.is_empty() {
+            body["system"] = Value::String(system);
+        }
+        if !tools.is_empty() {
+            body["tools"] = tools.iter().map(anthropic_tool).collect();
+        }
+        body
+    }
+}
+
+impl Default for AnthropicProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ChatProvider for AnthropicProvider {
+    async fn stream_chat(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> Result<ProviderStream<'static>> {
+        let mut events = self
+            .http
+            .post(format!("{}/messages", self.api_base.trim_end_matches('/')))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&self.request_body(messages, tools))
+            .eventsource()?;
+
+        Ok(Box::pin(stream! {
+            let mut blocks: BTreeMap<usize, ToolCall> = BTreeMap::new();
+            while let Some(event) = events.next().await {
+                let message = match event {
+                    Ok(Event::Open) => continue,
+                    Ok(Event::Message(message)) => message,
+                    Err(EventSourceError::StreamEnded) => break,
+                    Err(EventSourceError::InvalidStatusCode(status, response)) => {
+                        let body = response.text().await.unwrap_or_default();
+                        yield Err(anyhow!("anthropic request failed ({status}): {body}"));
+                        break;
+                    }
+                    Err(error) => {
+                        yield Err(anyhow::Error::from(error));
+                        break;
+                    }
+                };
+                let event = match serde_json::from_str::<StreamEvent>(&message.data) {
+                    Ok(event) => event,
+                    Err(error) => {
+                        yield Err(anyhow!("unexpected anthropic event {:?}: {error}", message.event));
+                        continue;
+                    }
+                };
+                match event {
+                    StreamEvent::ContentBlockStart {
+                        index,
+                        content_block: ContentBlock::ToolUse { id, name },
+                    } => {
+                        blocks.insert(index, ToolCall { id, name, json: String::new() });
+                    }
+                    StreamEvent::ContentBlockStart {
+                        content_block: ContentBlock::Text { text },
+                        ..
+                    } if !text.is_empty() => yield Ok(ChatDelta::Content(text)),
+                    StreamEvent::ContentBlockDelta { index, delta } => match delta {
+                        BlockDelta::TextDelta { text } => yield Ok(ChatDelta::Content(text)),
+                        BlockDelta::InputJsonDelta { partial_json } => {
+                            if let Some(call) = blocks.get_mut(&index) {
+                                call.json.push_str(&partial_json);
+                            }
+                        }
+                        BlockDelta::Other => {}
+                    },
+                    StreamEvent::ContentBlockStop { index } => {
+                        if let Some(mut call) = blocks.remove(&index) {
+                            if call.json.is_empty() {
+                                call.json = "{}".to_string();
+                            }
+                            yield Ok(ChatDelta::ToolCall(call));
+                        }
+                    }
+                    StreamEvent::MessageStop => break,
+                    StreamEvent::Error { error } => {
+                        yield Err(anyhow!("anthropic stream error: {}", error.message));
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            events.close();
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: BlockDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageStop,
+    Error {
+        error: StreamError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamError {
+    message: String,
+}
+
+fn anthropic_tool(tool: &ToolDefinition) -> Value {
+    let mut input_schema = tool.parameters.clone();
+    if let Some(object) = input_schema.as_object_mut() {
+        object.remove("$schema");
+    }
+    json!({
+        "name": tool.name,
+        "description": tool.description,
+        "input_schema": input_schema,
+    })
+}
+
+/// Converts the history into alternating user/assistant turns.
+///
+/// System messages are hoisted into the top-level `system` field, and tool
+/// results become `tool_result` blocks on a user turn. Consecutive turns from
+/// the same role are merged, since the API requires strict alternation.
+fn anthropic_messages(messages: &[Message]) -> Vec<Value> {
+    let mut turns: Vec<(&str, Vec<Value>)> = vec![];
+    for message in messages {
+        let (role, blocks) = match message {
+            Message::System { .. } => continue,
+            Message::User { content } => ("user", vec![json!({"type": "text", "text": content})]),
+            Message::Assistant {
+                content,
+                tool_calls,
+            } => {
+                let mut blocks = vec![];
+                if let Some(text) = content.as_deref().filter(|text| !text.is_empty()) {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                for call in tool_calls {
+                    let input = serde_json::from_str::<Value>(&call.json)
+                        .ok()
+                        .filter(Value::is_object)
+                        .unwrap_or_else(|| json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.name,
+                        "input": input,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            Message::Tool {
+                tool_call_id,
+                content,
+                status,
+            } => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": tool_call_id,
+                    "content": content,
+                    "is_error": *status != ToolStatus::Ok,
+                })],
+            ),
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+    turns
+        .into_iter()
+        .map(|(role, content)| json!({"role": role, "content": content}))
+        .collect()
+}
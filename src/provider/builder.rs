@@ -0,0 +1,128 @@
+use super::anthropic::{self, AnthropicProvider};
+use super::chat_provider::ChatProvider;
+use super::openai::{self, OpenAIProvider};
+
+use anyhow::{Result, anyhow};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    OpenAI,
+    Anthropic,
+}
+
+impl FromStr for ProviderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "openai" => Ok(ProviderKind::OpenAI),
+            "anthropic" => Ok(ProviderKind::Anthropic),
+            other => Err(anyhow!(
+                "unknown provider '{other}' (expected openai or anthropic)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProviderKind::OpenAI => "openai",
+            ProviderKind::Anthropic => "anthropic",
+        })
+    }
+}
+
+/// Connection and sampling settings for a model backend.
+///
+/// Unset values fall back to the backend's defaults, so an OpenAI builder with
+/// only `api_base` set can target any OpenAI-compatible server (Ollama, vLLM,
+/// llama.cpp).
+#[derive(Debug, Clone, Default)]
+pub struct ProviderBuilder {
+    pub kind: ProviderKind,
+    pub api_base: Option<String>,
+    pub api_key: Option<String>,
+    pub org_id: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl ProviderBuilder {
+    pub fn new(kind: ProviderKind) -> Self {
+        Self {
+            kind,
+            ..Default::default()
+        }
+    }
+
+    pub fn api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = Some(api_base.into());
+        self
+    }
+
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn org_id(mut self, org_id: impl Into<String>) -> Self {
+        self.org_id = Some(org_id.into());
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The configured model, or the backend's default.
+    pub fn resolved_model(&self) -> &str {
+        self.model.as_deref().unwrap_or(match self.kind {
+            ProviderKind::OpenAI => openai::DEFAULT_MODEL,
+            ProviderKind::Anthropic => anthropic::DEFAULT_MODEL,
+        })
+    }
+
+    pub fn build(&self) -> Result<Box<dyn ChatProvider>> {
+        Ok(match self.kind {
+            ProviderKind::OpenAI => Box::new(OpenAIProvider::from_builder(self)?),
+            ProviderKind::Anthropic => Box::new(AnthropicProvider::from_builder(self)?),
+        })
+    }
+
+    pub(crate) fn header_map(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_str(name).map_err(|_| anyhow!("invalid header name '{name}'"))?,
+                HeaderValue::from_str(value)
+                    .map_err(|_| anyhow!("invalid value for header '{name}'"))?,
+            );
+        }
+        Ok(headers)
+    }
+}
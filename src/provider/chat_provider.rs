@@ -0,0 +1,18 @@
+use super::types::{Message, ProviderStream, ToolDefinition};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// A model backend that can stream a chat completion with tool calling.
+///
+/// Implementations translate the provider-neutral history and tool definitions
+/// into their own wire format and yield content deltas and fully assembled
+/// tool calls back.
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    async fn stream_chat(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> Result<ProviderStream<'static>>;
+}
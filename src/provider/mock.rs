@@ -0,0 +1,106 @@
+use super::chat_provider::ChatProvider;
+use super::openai::parse_stream;
+use super::types::{Message, ProviderStream, ToolDefinition};
+
+use anyhow::{Result, anyhow};
+use async_openai::error::OpenAIError;
+use async_openai::types::{
+    ChatCompletionMessageToolCallChunk, CreateChatCompletionStreamResponse, FinishReason,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// One streamed chunk, shaped like the `delta` of an OpenAI chat completion chunk.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockChunk {
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ChatCompletionMessageToolCallChunk>,
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub messages: Vec<Message>,
+    pub tools: Vec<ToolDefinition>,
+}
+
+/// Replays scripted model turns without touching the network.
+///
+/// Each call to `stream_chat` consumes the next turn and runs its chunks
+/// through the same parser as the OpenAI backend, so fragmented tool calls are
+/// assembled exactly as they would be against the real API.
+pub struct MockProvider {
+    turns: Mutex<VecDeque<Vec<MockChunk>>>,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockProvider {
+    pub fn new(turns: Vec<Vec<MockChunk>>) -> Self {
+        Self {
+            turns: Mutex::new(turns.into()),
+            requests: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Parses a fixture of the form `[[chunk, ...], ...]`, one array per turn.
+    pub fn from_json(fixture: &str) -> Result<Self> {
+        Ok(Self::new(serde_json::from_str(fixture)?))
+    }
+
+    /// Shared handle to every request received so far, kept valid after the
+    /// provider is moved into a conversation.
+    pub fn requests(&self) -> Arc<Mutex<Vec<MockRequest>>> {
+        self.requests.clone()
+    }
+}
+
+#[async_trait]
+impl ChatProvider for MockProvider {
+    async fn stream_chat(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> Result<ProviderStream<'static>> {
+        self.requests.lock().unwrap().push(MockRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+        });
+        let turn = self
+            .turns
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| anyhow!("mock provider has no scripted turns left"))?;
+        let chunks = turn
+            .into_iter()
+            .map(completion_chunk)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(parse_stream(Box::pin(futures::stream::iter(
+            chunks.into_iter().map(Ok::<_, OpenAIError>),
+        ))))
+    }
+}
+
+fn completion_chunk(chunk: MockChunk) -> Result<CreateChatCompletionStreamResponse> {
+    let value = json!({
+        "id": "mock",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": "mock",
+        "choices": [{
+            "index": 0,
+            "delta": {
+                "content": chunk.content,
+                "tool_calls": if chunk.tool_calls.is_empty() { None } else { Some(chunk.tool_calls) },
+            },
+            "finish_reason": chunk.finish_reason,
+        }],
+    });
+    Ok(serde_json::from_value(value)?)
+}
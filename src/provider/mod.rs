@@ -0,0 +1,13 @@
+mod anthropic;
+mod builder;
+mod chat_provider;
+mod mock;
+mod openai;
+mod types;
+
+pub use anthropic::*;
+pub use builder::*;
+pub use chat_provider::*;
+pub use mock::*;
+pub use openai::*;
+pub use types::*;
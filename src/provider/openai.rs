@@ -0,0 +1,234 @@
+use super::builder::ProviderBuilder;
+use super::chat_provider::ChatProvider;
+use super::types::{ChatDelta, Message, ProviderStream, ToolCall, ToolDefinition};
+
+use anyhow::Result;
+use async_openai::Client;
+use async_openai::config::{Config, OpenAIConfig};
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+    ChatCompletionRequestAssistantMessageContent, ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessage, ChatCompletionRequestSystemMessageContent,
+    ChatCompletionRequestToolMessage, ChatCompletionRequestToolMessageContent,
+    ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent,
+    ChatCompletionResponseStream, ChatCompletionTool, ChatCompletionToolType,
+    CreateChatCompletionRequestArgs, FinishReason, FunctionCall, FunctionObject,
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use std::collections::BTreeMap;
+
+pub(crate) const DEFAULT_MODEL: &str = "gpt-4.1";
+
+pub struct OpenAIProvider<C: Config = OpenAIConfig> {
+    client: Client<C>,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    /// Sends the limit as `max_tokens`, which OpenAI deprecated but most
+    /// compatible servers still expect, instead of `max_completion_tokens`.
+    legacy_max_tokens: bool,
+}
+
+impl OpenAIProvider<OpenAIConfig> {
+    pub fn new() -> Self {
+        Self::with_client(Client::new(), DEFAULT_MODEL)
+    }
+
+    pub fn from_builder(builder: &ProviderBuilder) -> Result<Self> {
+        let mut config = OpenAIConfig::new();
+        if let Some(api_base) = &builder.api_base {
+            config = config.with_api_base(api_base);
+        }
+        if let Some(api_key) = &builder.api_key {
+            config = config.with_api_key(api_key);
+        }
+        if let Some(org_id) = &builder.org_id {
+            config = config.with_org_id(org_id);
+        }
+        let http_client = reqwest::Client::builder()
+            .default_headers(builder.header_map()?)
+            .build()?;
+        let client = Client::with_config(config).with_http_client(http_client);
+        let model = builder.model.as_deref().unwrap_or(DEFAULT_MODEL);
+
+        Ok(Self {
+            temperature: builder.temperature,
+            max_tokens: builder.max_tokens,
+            legacy_max_tokens: builder
+                .api_base
+                .as_deref()
+                .is_some_and(|api_base| !api_base.contains("api.openai.com")),
+            ..Self::with_client(client, model)
+        })
+    }
+}
+
+impl Default for OpenAIProvider<OpenAIConfig> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Config> OpenAIProvider<C> {
+    pub fn with_client(client: Client<C>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+            temperature: None,
+            max_tokens: None,
+            legacy_max_tokens: false,
+        }
+    }
+}
+
+#[async_trait]
+impl<C> ChatProvider for OpenAIProvider<C>
+where
+    C: Config + Send + Sync,
+{
+    async fn stream_chat(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> Result<ProviderStream<'static>> {
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request
+            .model(self.model.clone())
+            .messages(messages.iter().map(openai_message).collect::<Vec<_>>())
+            .n(1);
+        if let Some(temperature) = self.temperature {
+            request.temperature(temperature);
+        }
+        match self.max_tokens {
+            #[allow(deprecated)]
+            Some(max_tokens) if self.legacy_max_tokens => request.max_tokens(max_tokens),
+            Some(max_tokens) => request.max_completion_tokens(max_tokens),
+            None => &mut request,
+        };
+        // the API rejects an empty tool list
+        if !tools.is_empty() {
+            request.tools(
+                tools
+                    .iter()
+                    .map(ChatCompletionTool::from)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let stream = self.client.chat().create_stream(request.build()?).await?;
+        Ok(parse_stream(stream))
+    }
+}
+
+impl From<&ToolDefinition> for ChatCompletionTool {
+    fn from(value: &ToolDefinition) -> Self {
+        Self {
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionObject {
+                name: value.name.clone(),
+                description: Some(value.description.clone()),
+                parameters: Some(value.parameters.clone()),
+                strict: value.strict.then_some(true),
+            },
+        }
+    }
+}
+
+pub fn openai_message(message: &Message) -> ChatCompletionRequestMessage {
+    match message {
+        Message::System { content } => {
+            ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                content: ChatCompletionRequestSystemMessageContent::Text(content.clone()),
+                name: None,
+            })
+        }
+        Message::User { content } => {
+            ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+                content: ChatCompletionRequestUserMessageContent::Text(content.clone()),
+                name: None,
+            })
+        }
+        Message::Assistant {
+            content,
+            tool_calls,
+        } => ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+            content: content
+                .clone()
+                .map(ChatCompletionRequestAssistantMessageContent::Text),
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(
+                    tool_calls
+                        .iter()
+                        .map(|call| ChatCompletionMessageToolCall {
+                            id: call.id.clone(),
+                            r#type: ChatCompletionToolType::Function,
+                            function: FunctionCall {
+                                arguments: call.json.clone(),
+                                name: call.name.clone(),
+                            },
+                        })
+                        .collect(),
+                )
+            },
+            ..Default::default()
+        }),
+        Message::Tool {
+            tool_call_id,
+            content,
+            ..
+        } => ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+            content: ChatCompletionRequestToolMessageContent::Text(content.clone()),
+            tool_call_id: tool_call_id.clone(),
+        }),
+    }
+}
+
+/// Turns raw chat completion chunks into content and fully assembled tool calls.
+///
+/// Tool call fragments are accumulated by index and emitted, in index order,
+/// once the model finishes with `tool_calls` (or the stream ends).
+pub fn parse_stream(mut stream: ChatCompletionResponseStream) -> ProviderStream<'static> {
+    Box::pin(stream! {
+        let mut tool_calls: BTreeMap<u32, ToolCall> = BTreeMap::new();
+        while let Some(part) = stream.next().await {
+            let part = match part {
+                Ok(part) => part,
+                Err(error) => {
+                    yield Err(anyhow::Error::from(error));
+                    continue;
+                }
+            };
+            let Some(first) = part.choices.first() else {
+                continue;
+            };
+            if let Some(content) = &first.delta.content {
+                yield Ok(ChatDelta::Content(content.clone()));
+            }
+            for call in first.delta.tool_calls.as_deref().unwrap_or_default() {
+                let partial = tool_calls.entry(call.index).or_default();
+                if let Some(id) = &call.id {
+                    partial.id = id.clone();
+                }
+                if let Some(function) = &call.function {
+                    if let Some(name) = &function.name {
+                        partial.name.push_str(name);
+                    }
+                    if let Some(arguments) = &function.arguments {
+                        partial.json.push_str(arguments);
+                    }
+                }
+            }
+            if let Some(FinishReason::ToolCalls) = first.finish_reason {
+                for call in std::mem::take(&mut tool_calls).into_values() {
+                    yield Ok(ChatDelta::ToolCall(call));
+                }
+            }
+        }
+        for call in tool_calls.into_values() {
+            yield Ok(ChatDelta::ToolCall(call));
+        }
+    })
+}
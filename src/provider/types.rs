@@ -0,0 +1,52 @@
+use crate::types::ToolStatus;
+
+use anyhow::Result;
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::pin::Pin;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum Message {
+    System {
+        content: String,
+    },
+    User {
+        content: String,
+    },
+    Assistant {
+        content: Option<String>,
+        tool_calls: Vec<ToolCall>,
+    },
+    Tool {
+        tool_call_id: String,
+        content: String,
+        #[serde(default)]
+        status: ToolStatus,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub json: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+    /// Ask the provider to enforce `parameters` exactly (OpenAI strict mode).
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum ChatDelta {
+    Content(String),
+    ToolCall(ToolCall),
+}
+
+pub type ProviderStream<'a> = Pin<Box<dyn Stream<Item = Result<ChatDelta>> + Send + 'a>>;
@@ -0,0 +1,3 @@
+mod transcript;
+
+pub use transcript::*;
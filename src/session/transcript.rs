@@ -0,0 +1,224 @@
+use crate::provider::Message;
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const TRANSCRIPT_EXTENSION: &str = "jsonl";
+
+/// One line of a transcript file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptRecord {
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub message: Message,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub id: String,
+    pub started: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+    pub messages: usize,
+    pub first_prompt: Option<String>,
+}
+
+/// An append-only JSONL transcript of one conversation.
+///
+/// System messages are not recorded; they are re-assembled on resume so that
+/// prompt changes apply to old sessions too.
+pub struct Session {
+    id: String,
+    path: PathBuf,
+    file: File,
+    model: Option<String>,
+}
+
+/// `<data dir>/hex/sessions`, e.g. `~/.local/share/hex/sessions`.
+pub fn sessions_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("hex").join("sessions"))
+}
+
+impl Session {
+    pub fn create(dir: &Path, model: Option<String>) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("could not create {}", dir.display()))?;
+        loop {
+            let now = Utc::now();
+            let id = format!(
+                "{}-{:04x}",
+                now.format("%Y%m%d-%H%M%S"),
+                now.timestamp_subsec_nanos() & 0xffff
+            );
+            let path = transcript_path(dir, &id);
+            // another session may have taken the id, e.g. one started by a
+            // second hex in the same instant
+            match OpenOptions::new().append(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    return Ok(Self {
+                        id,
+                        path,
+                        file,
+                        model,
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("could not create {}", path.display()));
+                }
+            }
+        }
+    }
+
+    /// Reopens an existing session for appending and returns its history.
+    /// Ids other than those `create` generates are rejected, so that they
+    /// can't name files outside of `dir`.
+    pub fn resume(dir: &Path, id: &str, model: Option<String>) -> Result<(Self, Vec<Message>)> {
+        if !is_session_id(id) {
+            return Err(anyhow!(
+                "invalid session id '{id}', expected one like 20250101-120000-00ff"
+            ));
+        }
+        let path = transcript_path(dir, id);
+        if !path.is_file() {
+            return Err(anyhow!("no session '{id}' in {}", dir.display()));
+        }
+        let messages = read_transcript(&path)?
+            .into_iter()
+            .map(|record| record.message)
+            .collect();
+        Ok((Self::open_file(dir, id.to_string(), model)?, messages))
+    }
+
+    /// The id of the most recently written session, if any.
+    pub fn latest(dir: &Path) -> Result<Option<String>> {
+        Ok(transcript_paths(dir)?
+            .into_iter()
+            .filter_map(|path| {
+                let modified = path.metadata().and_then(|meta| meta.modified()).ok()?;
+                Some((modified, session_id(&path)?))
+            })
+            .max()
+            .map(|(_, id)| id))
+    }
+
+    /// Summaries of every session, oldest first.
+    pub fn list(dir: &Path) -> Result<Vec<SessionSummary>> {
+        let mut summaries = transcript_paths(dir)?
+            .into_iter()
+            .filter_map(|path| {
+                let id = session_id(&path)?;
+                let records = read_transcript(&path).ok()?;
+                Some(SessionSummary {
+                    id,
+                    started: records.first().map(|record| record.timestamp),
+                    updated: records.last().map(|record| record.timestamp),
+                    messages: records.len(),
+                    first_prompt: records.into_iter().find_map(|record| match record.message {
+                        Message::User { content } => Some(content),
+                        _ => None,
+                    }),
+                })
+            })
+            .collect::<Vec<_>>();
+        summaries.sort_by(|a, b| a.started.cmp(&b.started).then(a.id.cmp(&b.id)));
+        Ok(summaries)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&mut self, messages: &[Message]) -> Result<()> {
+        for message in messages {
+            if matches!(message, Message::System { .. }) {
+                continue;
+            }
+            let record = TranscriptRecord {
+                timestamp: Utc::now(),
+                model: self.model.clone(),
+                message: message.clone(),
+            };
+            serde_json::to_writer(&mut self.file, &record)?;
+            self.file.write_all(b"\n")?;
+        }
+        self.file
+            .flush()
+            .with_context(|| format!("could not write {}", self.path.display()))
+    }
+
+    fn open_file(dir: &Path, id: String, model: Option<String>) -> Result<Self> {
+        let path = transcript_path(dir, &id);
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        Ok(Self {
+            id,
+            path,
+            file,
+            model,
+        })
+    }
+}
+
+pub fn read_transcript(path: &Path) -> Result<Vec<TranscriptRecord>> {
+    let file = File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(number, line)| {
+            serde_json::from_str(&line?)
+                .with_context(|| format!("{}:{}: invalid record", path.display(), number + 1))
+        })
+        .collect()
+}
+
+fn transcript_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(id).with_extension(TRANSCRIPT_EXTENSION)
+}
+
+fn transcript_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    Ok(std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == TRANSCRIPT_EXTENSION)
+        })
+        .collect())
+}
+
+fn session_id(path: &Path) -> Option<String> {
+    let id = path.file_stem()?.to_str()?;
+    is_session_id(id).then(|| id.to_string())
+}
+
+/// Whether `id` has the `YYYYMMDD-HHMMSS-xxxx` shape `Session::create` gives.
+fn is_session_id(id: &str) -> bool {
+    let parts = id.split('-').collect::<Vec<_>>();
+    let [date, time, suffix] = parts[..] else {
+        return false;
+    };
+    date.len() == 8
+        && date.bytes().all(|byte| byte.is_ascii_digit())
+        && time.len() == 6
+        && time.bytes().all(|byte| byte.is_ascii_digit())
+        && suffix.len() == 4
+        && suffix
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+
+/// A tool call waiting to be approved, with its validated arguments.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApprovalRequest {
+    pub tool: String,
+    pub arguments: Value,
+    /// Whether the tool is marked as changing something, such as files.
+    pub mutating: bool,
+}
+
+impl ApprovalRequest {
+    /// The files the call names in a `path` or `paths` argument.
+    pub fn paths(&self) -> Vec<&str> {
+        let path = self.arguments.get("path").and_then(Value::as_str);
+        let paths = self
+            .arguments
+            .get("paths")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str);
+        path.into_iter().chain(paths).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    Approved,
+    /// The reason is reported to the model as a denied result.
+    Denied(String),
+}
+
+/// Decides whether a tool call may run. A toolset with an approver consults it
+/// before every call; see `AsyncToolSet::with_approver`.
+#[async_trait]
+pub trait Approver: Send + Sync {
+    async fn approve(&self, request: &ApprovalRequest) -> anyhow::Result<Approval>;
+}
@@ -0,0 +1,66 @@
+use super::output::ToolOutput;
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::future::Future;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+pub type DynamicHandler<C> = Arc<
+    dyn Fn(Value, &C, CancellationToken) -> BoxFuture<'static, Result<ToolOutput, anyhow::Error>>
+        + Send
+        + Sync,
+>;
+
+/// A tool whose argument schema is only known at runtime, e.g. loaded from a
+/// config file, a plugin or a remote server. The handler receives the
+/// arguments as JSON once they have been validated against `schema`, and a
+/// token that fires when the call is cancelled or times out.
+pub struct DynamicTool<C> {
+    pub name: String,
+    pub description: String,
+    pub schema: Value,
+    pub handler: DynamicHandler<C>,
+    /// See `ToolSet::mutating`.
+    pub mutating: bool,
+}
+
+impl<C> DynamicTool<C> {
+    pub fn new<F, Fut>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        schema: Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value, &C, CancellationToken) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolOutput, anyhow::Error>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            schema,
+            handler: Arc::new(move |arguments, context, cancel| {
+                Box::pin(handler(arguments, context, cancel))
+            }),
+            mutating: false,
+        }
+    }
+
+    pub fn with_mutating(mut self, mutating: bool) -> Self {
+        self.mutating = mutating;
+        self
+    }
+}
+
+impl<C> Clone for DynamicTool<C> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            schema: self.schema.clone(),
+            handler: self.handler.clone(),
+            mutating: self.mutating,
+        }
+    }
+}
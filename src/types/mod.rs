@@ -1,5 +1,15 @@
+mod approval;
+mod dynamic;
+mod output;
+mod progress;
 mod tool;
 mod toolset;
 
+pub use approval::*;
+pub use dynamic::*;
+pub use hex_derive::HexTool;
+pub use output::*;
+pub use progress::*;
+pub use tokio_util::sync::CancellationToken;
 pub use tool::*;
 pub use toolset::*;
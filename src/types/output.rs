@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolStatus {
+    #[default]
+    Ok,
+    Error,
+    /// The user or an approval policy refused to run the tool.
+    Denied,
+}
+
+impl fmt::Display for ToolStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ToolStatus::Ok => "ok",
+            ToolStatus::Error => "error",
+            ToolStatus::Denied => "denied",
+        })
+    }
+}
+
+/// The result of running a tool.
+///
+/// `text` is what the model reads; `data` is an optional machine-readable
+/// payload for clients, only sent to the model when there is no text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolOutput {
+    pub status: ToolStatus,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl ToolOutput {
+    pub fn ok(text: impl Into<String>) -> Self {
+        Self::new(ToolStatus::Ok, text)
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self::new(ToolStatus::Error, text)
+    }
+
+    pub fn denied(text: impl Into<String>) -> Self {
+        Self::new(ToolStatus::Denied, text)
+    }
+
+    fn new(status: ToolStatus, text: impl Into<String>) -> Self {
+        Self {
+            status,
+            text: text.into(),
+            data: None,
+        }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == ToolStatus::Ok
+    }
+
+    /// The tool message content sent back to the model.
+    pub fn model_content(&self) -> String {
+        let body = match (&self.data, self.text.is_empty()) {
+            (Some(data), true) => data.to_string(),
+            _ => self.text.clone(),
+        };
+        match self.status {
+            ToolStatus::Ok => body,
+            ToolStatus::Error => format!("error: {body}"),
+            ToolStatus::Denied => format!("denied: {body}"),
+        }
+    }
+}
+
+impl From<String> for ToolOutput {
+    fn from(text: String) -> Self {
+        Self::ok(text)
+    }
+}
+
+impl From<&str> for ToolOutput {
+    fn from(text: &str) -> Self {
+        Self::ok(text)
+    }
+}
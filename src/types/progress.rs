@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// An update from a running tool. Clients show it live; the model only sees
+/// the tool's final output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Log(String),
+    /// How much of the work is done, from 0 to 100.
+    Percent(f32),
+}
+
+impl fmt::Display for ProgressEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgressEvent::Log(line) => f.write_str(line),
+            ProgressEvent::Percent(percent) => write!(f, "{percent:.0}%"),
+        }
+    }
+}
+
+/// Where a [`StreamingTool`](crate::types::StreamingTool) reports progress.
+/// The default discards every event, e.g. when a tool is called outside of a
+/// conversation.
+#[derive(Clone, Default)]
+pub struct Progress {
+    report: Option<Arc<dyn Fn(ProgressEvent) + Send + Sync>>,
+}
+
+impl Progress {
+    pub fn new(report: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            report: Some(Arc::new(report)),
+        }
+    }
+
+    pub fn report(&self, event: ProgressEvent) {
+        if let Some(report) = &self.report {
+            report(event);
+        }
+    }
+
+    pub fn log(&self, line: impl Into<String>) {
+        self.report(ProgressEvent::Log(line.into()));
+    }
+
+    /// Clamped to 0..=100.
+    pub fn percent(&self, percent: f32) {
+        self.report(ProgressEvent::Percent(percent.clamp(0.0, 100.0)));
+    }
+}
@@ -1,28 +1,139 @@
+use super::output::ToolOutput;
+use super::progress::Progress;
+
+use anyhow::anyhow;
 use async_trait::async_trait;
+use std::any::Any;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
 pub trait Tool {
     type Context;
-    fn apply(&self, context: Self::Context) -> String;
+    fn apply(&self, context: Self::Context) -> Result<ToolOutput, anyhow::Error>;
 }
 
 #[async_trait]
 pub trait AsyncTool {
     type Context;
-    async fn apply(&self, context: Self::Context) -> Result<String, anyhow::Error>;
+    /// `cancel` fires when the user interrupts the call. The returned future
+    /// is dropped at that point anyway, so only tools with work outside of it,
+    /// such as spawned tasks or processes, need to watch the token.
+    async fn apply(
+        &self,
+        context: Self::Context,
+        cancel: CancellationToken,
+    ) -> Result<ToolOutput, anyhow::Error>;
+}
+
+/// An async tool that reports progress while it runs, such as a build or a
+/// repository scan. Only the returned output reaches the model.
+#[async_trait]
+pub trait StreamingTool {
+    type Context;
+    async fn apply(
+        &self,
+        context: Self::Context,
+        progress: Progress,
+        cancel: CancellationToken,
+    ) -> Result<ToolOutput, anyhow::Error>;
+}
+
+/// One call to a [`StreamingTool`], holding where its progress goes.
+pub(crate) struct StreamingToolWrapper<T> {
+    pub tool: T,
+    pub progress: Progress,
+}
+
+#[async_trait]
+impl<T> AsyncTool for StreamingToolWrapper<T>
+where
+    T: StreamingTool + Send + Sync,
+    T::Context: Send + 'static,
+{
+    type Context = T::Context;
+    async fn apply(
+        &self,
+        context: Self::Context,
+        cancel: CancellationToken,
+    ) -> Result<ToolOutput, anyhow::Error> {
+        self.tool
+            .apply(context, self.progress.clone(), cancel)
+            .await
+    }
 }
 
+/// Why a tool call ended without a result. Reported to the model as the
+/// tool's error.
+#[derive(Debug, Error)]
+pub enum ToolInterrupted {
+    #[error("timed out after {0:?}")]
+    TimedOut(Duration),
+    #[error("cancelled by the user")]
+    Cancelled,
+}
+
+/// Runs a sync tool on tokio's blocking thread pool, so that file I/O or a
+/// prompt doesn't stall the runtime streaming the model's output.
 pub struct AsyncToolWrapper<C> {
-    pub tool: Box<dyn Tool<Context = C> + Send + Sync>,
+    pub tool: Arc<dyn Tool<Context = C> + Send + Sync>,
 }
 
 #[async_trait]
 impl<C> AsyncTool for AsyncToolWrapper<C>
 where
-    C: Send + Sync,
+    C: Send + Sync + 'static,
 {
     type Context = C;
-    async fn apply(&self, context: Self::Context) -> Result<String, anyhow::Error> {
-        Ok(self.tool.apply(context))
+    async fn apply(
+        &self,
+        context: Self::Context,
+        cancel: CancellationToken,
+    ) -> Result<ToolOutput, anyhow::Error> {
+        let tool = self.tool.clone();
+        // a blocking task can't be aborted, so dropping the call only cancels
+        // the token the tool can poll
+        let cancelled = cancel.child_token();
+        let _cancel_on_drop = cancelled.clone().drop_guard();
+        tokio::task::spawn_blocking(move || {
+            CANCELLED.with(|current| current.replace(Some(cancelled)));
+            let result = tool.apply(context);
+            CANCELLED.with(|current| current.replace(None));
+            result
+        })
+        .await
+        .map_err(|err| match err.try_into_panic() {
+            Ok(panic) => anyhow!("the tool panicked: {}", panic_message(&*panic)),
+            Err(err) => anyhow!(err),
+        })?
     }
 }
 
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message")
+}
+
+thread_local! {
+    static CANCELLED: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// Whether the sync tool running on this thread was cancelled or is no
+/// longer awaited, e.g. because it timed out. Its result will be discarded, so
+/// long-running tools can check this and return early.
+pub fn sync_tool_cancelled() -> bool {
+    CANCELLED.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct NoContext();
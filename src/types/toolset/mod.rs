@@ -1,5 +1,9 @@
+mod strict;
 pub mod tool_object;
+#[allow(clippy::module_inception)]
 mod toolset;
 pub mod types;
 
+pub use tool_object::ToolObject;
 pub use toolset::*;
+pub use types::*;
@@ -0,0 +1,271 @@
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// String formats accepted by OpenAI structured outputs.
+const STRICT_FORMATS: &[&str] = &[
+    "date-time",
+    "time",
+    "date",
+    "duration",
+    "email",
+    "hostname",
+    "ipv4",
+    "ipv6",
+    "uuid",
+];
+
+/// Rewrites a schemars schema into the subset accepted by OpenAI strict mode:
+/// definitions are inlined, every object lists all of its properties as
+/// required and forbids additional ones, and optional properties become
+/// nullable instead.
+///
+/// Also returns the properties it made nullable, so that the nulls the model
+/// sends for them can be pruned again.
+///
+/// Fails with a reason when the schema has no strict equivalent, such as maps,
+/// tuples or recursive types.
+pub fn strict_schema(schema: &Value) -> Result<(Value, Optionals), String> {
+    let definitions = schema
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let mut root = schema.clone();
+    if let Some(root) = root.as_object_mut() {
+        root.remove("$schema");
+        root.remove("title");
+        root.remove("definitions");
+    }
+    let (root, optionals) = Normalizer {
+        definitions: &definitions,
+        expanding: vec![],
+    }
+    .normalize(root)?;
+    if root.get("type") != Some(&json!("object")) {
+        return Err("arguments must be an object".to_string());
+    }
+    Ok((root, optionals))
+}
+
+/// The optional properties of a schema that strict mode made nullable,
+/// following the shape of the arguments.
+#[derive(Debug, Default)]
+pub struct Optionals {
+    nullable: BTreeSet<String>,
+    properties: BTreeMap<String, Optionals>,
+    items: Option<Box<Optionals>>,
+}
+
+impl Optionals {
+    fn is_empty(&self) -> bool {
+        self.nullable.is_empty() && self.properties.is_empty() && self.items.is_none()
+    }
+
+    /// Combines the optionals of `anyOf` variants, since any of them may
+    /// describe the value.
+    fn merge(&mut self, other: Optionals) {
+        self.nullable.extend(other.nullable);
+        for (name, property) in other.properties {
+            self.properties.entry(name).or_default().merge(property);
+        }
+        if let Some(items) = other.items {
+            self.items.get_or_insert_default().merge(*items);
+        }
+    }
+
+    /// Drops `null` members for the properties made nullable, so a strict-mode
+    /// "nullable optional" reaches serde as a missing field again. Other
+    /// nulls are left alone.
+    pub fn prune_nulls(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.retain(|name, value| !(value.is_null() && self.nullable.contains(name)));
+                for (name, property) in &self.properties {
+                    if let Some(value) = object.get_mut(name) {
+                        property.prune_nulls(value);
+                    }
+                }
+            }
+            Value::Array(values) => {
+                if let Some(items) = &self.items {
+                    values.iter_mut().for_each(|value| items.prune_nulls(value));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Normalizer<'a> {
+    definitions: &'a Map<String, Value>,
+    expanding: Vec<String>,
+}
+
+impl Normalizer<'_> {
+    fn normalize(&mut self, schema: Value) -> Result<(Value, Optionals), String> {
+        let Value::Object(mut schema) = schema else {
+            return Err("boolean schemas are not supported".to_string());
+        };
+        schema.remove("default");
+        if schema
+            .get("format")
+            .and_then(Value::as_str)
+            .is_some_and(|format| !STRICT_FORMATS.contains(&format))
+        {
+            schema.remove("format");
+        }
+
+        if let Some(reference) = schema.remove("$ref") {
+            return self.inline(&reference, schema);
+        }
+        if let Some(Value::Array(mut all_of)) = schema.remove("allOf") {
+            // schemars wraps a described `$ref` in a single-element `allOf`
+            if all_of.len() != 1 {
+                return Err("`allOf` with several schemas is not supported".to_string());
+            }
+            return match all_of.remove(0) {
+                Value::Object(inner) => {
+                    let mut merged = inner;
+                    merged.extend(schema);
+                    self.normalize(Value::Object(merged))
+                }
+                _ => Err("boolean schemas are not supported".to_string()),
+            };
+        }
+        if let Some(one_of) = schema.remove("oneOf") {
+            schema.insert("anyOf".to_string(), one_of);
+        }
+        let mut optionals = Optionals::default();
+        if let Some(Value::Array(any_of)) = schema.remove("anyOf") {
+            let mut variants = vec![];
+            for variant in any_of {
+                let (variant, variant_optionals) = self.normalize(variant)?;
+                variants.push(variant);
+                optionals.merge(variant_optionals);
+            }
+            schema.insert("anyOf".to_string(), Value::Array(variants));
+        }
+
+        if let Some(items) = schema.remove("items") {
+            if items.is_array() {
+                return Err("tuples are not supported".to_string());
+            }
+            let (items, item_optionals) = self.normalize(items)?;
+            schema.insert("items".to_string(), items);
+            if !item_optionals.is_empty() {
+                optionals.items = Some(Box::new(item_optionals));
+            }
+        }
+
+        let is_object =
+            schema.get("type") == Some(&json!("object")) || schema.contains_key("properties");
+        if is_object {
+            self.normalize_object(&mut schema, &mut optionals)?;
+        }
+        Ok((Value::Object(schema), optionals))
+    }
+
+    fn inline(
+        &mut self,
+        reference: &Value,
+        siblings: Map<String, Value>,
+    ) -> Result<(Value, Optionals), String> {
+        let name = reference
+            .as_str()
+            .and_then(|reference| reference.strip_prefix("#/definitions/"))
+            .ok_or_else(|| format!("unsupported reference {reference}"))?;
+        if self.expanding.iter().any(|expanding| expanding == name) {
+            return Err(format!("recursive type `{name}` is not supported"));
+        }
+        let Some(Value::Object(definition)) = self.definitions.get(name) else {
+            return Err(format!("missing definition `{name}`"));
+        };
+        let mut merged = definition.clone();
+        merged.extend(siblings);
+        self.expanding.push(name.to_string());
+        let inlined = self.normalize(Value::Object(merged));
+        self.expanding.pop();
+        inlined
+    }
+
+    fn normalize_object(
+        &mut self,
+        schema: &mut Map<String, Value>,
+        optionals: &mut Optionals,
+    ) -> Result<(), String> {
+        match schema.get("additionalProperties") {
+            None | Some(Value::Bool(false)) => {}
+            Some(_) => return Err("maps and free-form objects are not supported".to_string()),
+        }
+        let required = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let properties = match schema.remove("properties") {
+            Some(Value::Object(properties)) => properties,
+            _ => Map::new(),
+        };
+        let mut normalized = Map::new();
+        for (name, property) in properties {
+            let (mut property, property_optionals) = self.normalize(property)?;
+            if !required.contains(&json!(name)) {
+                make_nullable(&mut property);
+                optionals.nullable.insert(name.clone());
+            }
+            if !property_optionals.is_empty() {
+                optionals
+                    .properties
+                    .entry(name.clone())
+                    .or_default()
+                    .merge(property_optionals);
+            }
+            normalized.insert(name, property);
+        }
+        let names = normalized.keys().cloned().map(Value::String).collect();
+        schema.insert("type".to_string(), json!("object"));
+        schema.insert("properties".to_string(), Value::Object(normalized));
+        schema.insert("required".to_string(), Value::Array(names));
+        schema.insert("additionalProperties".to_string(), json!(false));
+        Ok(())
+    }
+}
+
+fn make_nullable(schema: &mut Value) {
+    let null = json!("null");
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+    match object.get_mut("type") {
+        Some(Value::Array(types)) => {
+            if !types.contains(&null) {
+                types.push(null.clone());
+            }
+        }
+        Some(kind @ Value::String(_)) => *kind = json!([kind.clone(), null]),
+        _ => {
+            let null_schema = json!({ "type": "null" });
+            match object.get_mut("anyOf") {
+                Some(Value::Array(any_of)) => {
+                    if !any_of.contains(&null_schema) {
+                        any_of.push(null_schema);
+                    }
+                }
+                _ => {
+                    let description = object.remove("description");
+                    let inner = std::mem::take(object);
+                    object.insert("anyOf".to_string(), json!([inner, null_schema]));
+                    if let Some(description) = description {
+                        object.insert("description".to_string(), description);
+                    }
+                }
+            }
+            return;
+        }
+    }
+    if let Some(Value::Array(values)) = object.get_mut("enum")
+        && !values.contains(&Value::Null)
+    {
+        values.push(Value::Null);
+    }
+}
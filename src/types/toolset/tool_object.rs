@@ -1,19 +1,33 @@
-use crate::types::{AsyncTool, AsyncToolWrapper, Tool};
+use super::strict::{Optionals, strict_schema};
+use super::types::{ArgumentViolation, ToolCallError};
+use crate::provider::ToolDefinition;
+use crate::types::{
+    AsyncTool, AsyncToolWrapper, DynamicTool, Progress, StreamingTool, StreamingToolWrapper, Tool,
+    ToolOutput,
+};
 
 use async_openai::types::{ChatCompletionTool, ChatCompletionToolType, FunctionObject};
+use futures::future::LocalBoxFuture;
+use jsonschema::Validator;
 use schemars::JsonSchema;
-use schemars::schema::{Schema, SchemaObject};
+use schemars::schema::{RootSchema, Schema, SchemaObject};
 use schemars::schema_for;
-use serde::de::Deserialize;
+use serde::de::{Deserialize, DeserializeOwned};
 use serde_json::Error as JsonError;
 use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 
 type ToolTraitObject<T> = Box<dyn Tool<Context = T> + Send + Sync>;
-type Deserializer<T> = Box<dyn Fn(&str) -> Result<ToolTraitObject<T>, serde_json::Error>>;
+type Deserializer<T> = Box<dyn Fn(Value) -> Result<ToolTraitObject<T>, ToolCallError>>;
 
-type AsyncToolTraitObject<T> = Box<dyn AsyncTool<Context = T>>;
-type AsyncDeserializer<T> = Box<dyn Fn(&str) -> Result<AsyncToolTraitObject<T>, serde_json::Error>>;
+/// A validated call, ready to run with a context.
+pub type AsyncToolCall<T> = Box<
+    dyn FnOnce(T, CancellationToken) -> LocalBoxFuture<'static, Result<ToolOutput, anyhow::Error>>,
+>;
+type AsyncDeserializer<T> = Box<dyn Fn(Value, Progress) -> Result<AsyncToolCall<T>, ToolCallError>>;
 
 pub type SyncToolObject<Context> = ToolObject<Deserializer<Context>>;
 pub type AsyncToolObject<Context> = ToolObject<AsyncDeserializer<Context>>;
@@ -22,23 +36,81 @@ pub struct ToolObject<T> {
     pub json_schema: Value,
     pub description: String,
     pub name: String,
+    /// The normalized schema sent to the model when the toolset is strict.
+    pub strict_schema: Option<Value>,
+    /// Calls wait for earlier calls to finish and run alone.
+    pub sequential: bool,
+    /// Calls change something, so approvers treat them as more than reads.
+    pub mutating: bool,
+    /// Calls running longer fail with [`ToolInterrupted::TimedOut`](crate::types::ToolInterrupted).
+    pub timeout: Option<Duration>,
+    /// The properties `strict_schema` made nullable.
+    optionals: Optionals,
+    validator: Validator,
     deserializer: T,
 }
 
+impl<T> ToolObject<T> {
+    /// Fails with a reason if the schema has no strict-mode equivalent.
+    pub fn into_strict(mut self) -> Result<Self, String> {
+        let (schema, optionals) = strict_schema(&self.json_schema)?;
+        self.strict_schema = Some(schema);
+        self.optionals = optionals;
+        Ok(self)
+    }
+
+    /// The schema advertised to the model.
+    pub fn parameters(&self) -> &Value {
+        self.strict_schema.as_ref().unwrap_or(&self.json_schema)
+    }
+
+    /// Parses `data` and checks it against `json_schema`, reporting every
+    /// violation rather than just the first one serde would hit.
+    pub fn validate_arguments(&self, data: &str) -> Result<Value, ToolCallError> {
+        let mut arguments = serde_json::from_str::<Value>(data).map_err(|source| {
+            ToolCallError::Deserialization {
+                path: ".".to_string(),
+                source,
+            }
+        })?;
+        // strict mode sends null for omitted optionals
+        self.optionals.prune_nulls(&mut arguments);
+        let violations = self
+            .validator
+            .iter_errors(&arguments)
+            .map(|error| ArgumentViolation {
+                pointer: error.instance_path.to_string(),
+                message: error.to_string(),
+            })
+            .collect::<Vec<_>>();
+        if violations.is_empty() {
+            Ok(arguments)
+        } else {
+            Err(ToolCallError::InvalidArguments(violations))
+        }
+    }
+}
+
 impl<C> ToolObject<Deserializer<C>> {
-    pub fn try_deserialize(&self, data: &str) -> Result<ToolTraitObject<C>, serde_json::Error> {
+    pub fn try_deserialize(&self, data: &str) -> Result<ToolTraitObject<C>, ToolCallError> {
         let deserializer = &self.deserializer;
-        deserializer(data)
+        deserializer(self.validate_arguments(data)?)
     }
 }
 
 impl<C> ToolObject<AsyncDeserializer<C>> {
-    pub fn try_deserialize(
+    pub fn try_deserialize(&self, data: &str) -> Result<AsyncToolCall<C>, ToolCallError> {
+        self.try_deserialize_with_progress(data, Progress::default())
+    }
+
+    /// Like `try_deserialize`; a streaming tool reports to `progress`.
+    pub fn try_deserialize_with_progress(
         &self,
         data: &str,
-    ) -> Result<AsyncToolTraitObject<C>, serde_json::Error> {
+        progress: Progress,
+    ) -> Result<AsyncToolCall<C>, ToolCallError> {
         let deserializer = &self.deserializer;
-        deserializer(data)
+        deserializer(self.validate_arguments(data)?, progress)
     }
 }
 
@@ -48,6 +120,8 @@ pub enum ValidationError {
     MissingMetadata,
     #[error("could not convert to json")]
     JsonSerialization(JsonError),
+    #[error("invalid json schema: {0}")]
+    InvalidSchema(String),
 }
 
 impl<T> From<&ToolObject<T>> for ChatCompletionTool {
@@ -57,13 +131,24 @@ impl<T> From<&ToolObject<T>> for ChatCompletionTool {
             function: FunctionObject {
                 name: value.name.clone(),
                 description: Some(value.description.clone()),
-                parameters: Some(value.json_schema.clone()),
-                strict: None,
+                parameters: Some(value.parameters().clone()),
+                strict: value.strict_schema.is_some().then_some(true),
             },
         }
     }
 }
 
+impl<T> From<&ToolObject<T>> for ToolDefinition {
+    fn from(value: &ToolObject<T>) -> Self {
+        Self {
+            name: value.name.clone(),
+            description: value.description.clone(),
+            parameters: value.parameters().clone(),
+            strict: value.strict_schema.is_some(),
+        }
+    }
+}
+
 impl<C> ToolObject<Deserializer<C>> {
     pub fn try_from_tool<T>() -> Result<Self, ValidationError>
     where
@@ -76,8 +161,10 @@ impl<C> ToolObject<Deserializer<C>> {
         let json_schema =
             serde_json::to_value(schema.clone()).map_err(ValidationError::JsonSerialization)?;
 
-        let deserializer = Box::new(|data: &str| {
-            serde_json::from_str::<T>(data)
+        let validator = compile_schema(&json_schema)?;
+
+        let deserializer = Box::new(|arguments: Value| {
+            from_arguments::<T>(arguments)
                 .map(|tool| Box::new(tool) as Box<dyn Tool<Context = C> + Send + Sync>)
         });
 
@@ -85,6 +172,12 @@ impl<C> ToolObject<Deserializer<C>> {
             name,
             json_schema,
             description,
+            strict_schema: None,
+            optionals: Optionals::default(),
+            sequential: false,
+            mutating: false,
+            timeout: None,
+            validator,
             deserializer,
         })
     }
@@ -94,6 +187,7 @@ impl<C> ToolObject<AsyncDeserializer<C>> {
     pub fn try_from_tool<T>() -> Result<Self, ValidationError>
     where
         T: JsonSchema + AsyncTool<Context = C> + for<'de> Deserialize<'de> + 'static,
+        C: 'static,
     {
         let schema = schema_for!(&T);
 
@@ -102,39 +196,207 @@ impl<C> ToolObject<AsyncDeserializer<C>> {
         let json_schema =
             serde_json::to_value(schema.clone()).map_err(ValidationError::JsonSerialization)?;
 
-        let deserializer = Box::new(|data: &str| {
-            serde_json::from_str::<T>(data)
-                .map(|tool| Box::new(tool) as Box<dyn AsyncTool<Context = C>>)
+        let validator = compile_schema(&json_schema)?;
+
+        let deserializer =
+            Box::new(|arguments: Value, _| from_arguments::<T>(arguments).map(prepare));
+
+        Ok(Self {
+            name,
+            json_schema,
+            description,
+            strict_schema: None,
+            optionals: Optionals::default(),
+            sequential: false,
+            mutating: false,
+            timeout: None,
+            validator,
+            deserializer,
+        })
+    }
+}
+
+impl<C> ToolObject<AsyncDeserializer<C>> {
+    pub fn try_from_streaming_tool<T>() -> Result<Self, ValidationError>
+    where
+        T: JsonSchema
+            + StreamingTool<Context = C>
+            + Send
+            + Sync
+            + for<'de> Deserialize<'de>
+            + 'static,
+        C: Send + 'static,
+    {
+        let schema = schema_for!(&T);
+
+        let (name, description) = validate_tool_schema(&schema.schema)?;
+
+        let json_schema =
+            serde_json::to_value(schema.clone()).map_err(ValidationError::JsonSerialization)?;
+
+        let validator = compile_schema(&json_schema)?;
+
+        let deserializer = Box::new(|arguments: Value, progress: Progress| {
+            from_arguments::<T>(arguments)
+                .map(|tool| prepare(StreamingToolWrapper { tool, progress }))
         });
 
         Ok(Self {
             name,
             json_schema,
             description,
+            strict_schema: None,
+            optionals: Optionals::default(),
+            sequential: false,
+            mutating: false,
+            timeout: None,
+            validator,
             deserializer,
         })
     }
 }
 
+impl<C> ToolObject<AsyncDeserializer<C>> {
+    /// Wraps an async function taking the arguments `A`; `schema` is the
+    /// schema of `A` named by [`fn_schema`].
+    pub fn try_from_fn<A, F>(schema: &RootSchema, function: F) -> Result<Self, ValidationError>
+    where
+        A: JsonSchema + DeserializeOwned + Send + 'static,
+        C: Send + 'static,
+        F: AsyncFn(A, &C, CancellationToken) -> Result<ToolOutput, anyhow::Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let (name, description) = validate_tool_schema(&schema.schema)?;
+
+        let json_schema =
+            serde_json::to_value(schema.clone()).map_err(ValidationError::JsonSerialization)?;
+
+        let validator = compile_schema(&json_schema)?;
+
+        let function = Arc::new(function);
+        let deserializer = Box::new(move |arguments: Value, _| {
+            let function = function.clone();
+            from_arguments::<A>(arguments).map(|arguments| {
+                // the future borrows the context, so both move into it
+                Box::new(move |context: C, cancel| {
+                    Box::pin(async move { function(arguments, &context, cancel).await })
+                        as LocalBoxFuture<_>
+                }) as AsyncToolCall<C>
+            })
+        });
+
+        Ok(Self {
+            name,
+            json_schema,
+            description,
+            strict_schema: None,
+            optionals: Optionals::default(),
+            sequential: false,
+            mutating: false,
+            timeout: None,
+            validator,
+            deserializer,
+        })
+    }
+}
+
+impl<C> ToolObject<AsyncDeserializer<C>>
+where
+    C: Send + 'static,
+{
+    pub fn try_from_dynamic(tool: DynamicTool<C>) -> Result<Self, ValidationError> {
+        if tool.name.is_empty() {
+            return Err(ValidationError::MissingMetadata);
+        }
+        if tool.schema.get("type").and_then(Value::as_str) != Some("object") {
+            return Err(ValidationError::InvalidSchema(
+                "tool arguments must be an object".to_string(),
+            ));
+        }
+        let validator = compile_schema(&tool.schema)?;
+
+        let handler = tool.handler;
+        let deserializer = Box::new(move |arguments: Value, _| {
+            let handler = handler.clone();
+            Ok(Box::new(move |context: C, cancel| {
+                handler(arguments, &context, cancel) as LocalBoxFuture<_>
+            }) as AsyncToolCall<C>)
+        });
+
+        Ok(Self {
+            name: tool.name,
+            json_schema: tool.schema,
+            description: tool.description,
+            strict_schema: None,
+            optionals: Optionals::default(),
+            sequential: false,
+            mutating: tool.mutating,
+            timeout: None,
+            validator,
+            deserializer,
+        })
+    }
+}
+
+/// The schema of `A`, titled and described as the tool `name`.
+pub fn fn_schema<A: JsonSchema>(name: &str, description: &str) -> RootSchema {
+    let mut schema = schema_for!(A);
+    let metadata = schema.schema.metadata();
+    metadata.title = Some(name.to_string());
+    metadata.description = Some(description.to_string());
+    schema
+}
+
 impl<C> From<SyncToolObject<C>> for AsyncToolObject<C>
 where
     C: Send + Sync + 'static,
 {
     fn from(value: SyncToolObject<C>) -> Self {
-        let async_deserializer = Box::new(move |json: &str| {
-            (value.deserializer)(json).map(|trait_obj| {
-                Box::new(AsyncToolWrapper { tool: trait_obj }) as AsyncToolTraitObject<C>
+        let async_deserializer = Box::new(move |arguments: Value, _| {
+            (value.deserializer)(arguments).map(|trait_obj| {
+                prepare(AsyncToolWrapper {
+                    tool: Arc::from(trait_obj),
+                })
             })
         });
         Self {
             description: value.description,
             json_schema: value.json_schema,
             name: value.name,
+            strict_schema: value.strict_schema,
+            sequential: value.sequential,
+            mutating: value.mutating,
+            timeout: value.timeout,
+            optionals: value.optionals,
+            validator: value.validator,
             deserializer: async_deserializer,
         }
     }
 }
 
+fn prepare<T, C>(tool: T) -> AsyncToolCall<C>
+where
+    T: AsyncTool<Context = C> + 'static,
+    C: 'static,
+{
+    Box::new(move |context, cancel| Box::pin(async move { tool.apply(context, cancel).await }))
+}
+
+/// Like `serde_json::from_value`, but records where in the arguments it failed.
+fn from_arguments<T: DeserializeOwned>(arguments: Value) -> Result<T, ToolCallError> {
+    serde_path_to_error::deserialize(arguments).map_err(|err| ToolCallError::Deserialization {
+        path: err.path().to_string(),
+        source: err.into_inner(),
+    })
+}
+
+fn compile_schema(json_schema: &Value) -> Result<Validator, ValidationError> {
+    jsonschema::validator_for(json_schema)
+        .map_err(|err| ValidationError::InvalidSchema(err.to_string()))
+}
+
 fn validate_tool_schema(schema: &SchemaObject) -> Result<(String, String), ValidationError> {
     let name = schema
         .metadata
@@ -148,7 +410,7 @@ fn validate_tool_schema(schema: &SchemaObject) -> Result<(String, String), Valid
     let description = validate_tool_description(schema)?;
     Ok((name, description))
 }
-// this could probably be converted to a compile-time check with a macro
+// `#[derive(HexTool)]` checks this at compile time; hand-written schemas are checked here
 fn validate_tool_description(schema: &SchemaObject) -> Result<String, ValidationError> {
     // validate description on subschema
     let description = schema
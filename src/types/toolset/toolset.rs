@@ -1,11 +1,18 @@
-use super::tool_object::{AsyncToolObject, SyncToolObject};
+use super::tool_object::{AsyncToolObject, SyncToolObject, ToolObject, ValidationError, fn_schema};
 use super::types::*;
-use crate::types::{AsyncTool, Tool};
+use crate::provider::ToolDefinition;
+use crate::types::{
+    Approval, ApprovalRequest, Approver, AsyncTool, DynamicTool, Progress, StreamingTool, Tool,
+    ToolInterrupted, ToolOutput,
+};
 use async_openai::types::ChatCompletionTool;
 use schemars::schema::RootSchema;
 use schemars::{JsonSchema, schema_for};
-use serde::de::Deserialize;
+use serde::de::{Deserialize, DeserializeOwned};
 use std::collections::hash_map::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 pub type SyncToolSet<Context> = ToolSet<SyncToolObject<Context>>;
 pub type AsyncToolSet<Context> = ToolSet<AsyncToolObject<Context>>;
@@ -14,6 +21,8 @@ pub type AsyncToolSet<Context> = ToolSet<AsyncToolObject<Context>>;
 pub struct ToolSet<T> {
     pub schemas: Vec<RootSchema>,
     tools: HashMap<String, T>,
+    strict: bool,
+    approver: Option<Arc<dyn Approver>>,
 }
 
 impl<C> ToolSet<C> {
@@ -21,8 +30,155 @@ impl<C> ToolSet<C> {
         Self {
             schemas: vec![],
             tools: HashMap::new(),
+            strict: false,
+            approver: None,
         }
     }
+
+    /// A toolset whose definitions use OpenAI strict mode. `add_tool` fails for
+    /// tools whose schema cannot be expressed strictly.
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn tool_names(&self) -> Vec<&str> {
+        let mut names = self.tools.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// The registered tools, sorted by name.
+    pub fn tools(&self) -> Vec<&C> {
+        let mut tools = self.tools.iter().collect::<Vec<_>>();
+        tools.sort_by(|a, b| a.0.cmp(b.0));
+        tools.into_iter().map(|(_, tool)| tool).collect()
+    }
+
+    /// Keeps only the named tools. Names match ignoring case, `_` and `-`,
+    /// so `read_files` selects `ReadFiles`.
+    pub fn select<S: AsRef<str>>(mut self, names: &[S]) -> Result<Self, ToolCallError> {
+        let wanted = self.find_tools(names)?;
+        self.tools.retain(|name, _| wanted.contains(name));
+        self.schemas.retain(|schema| {
+            schema
+                .schema
+                .metadata
+                .as_deref()
+                .and_then(|metadata| metadata.title.as_ref())
+                .is_some_and(|title| wanted.contains(title))
+        });
+        Ok(self)
+    }
+
+    fn find_tools<S: AsRef<str>>(&self, names: &[S]) -> Result<Vec<String>, ToolCallError> {
+        names
+            .iter()
+            .map(|name| {
+                let name = name.as_ref();
+                self.tools
+                    .keys()
+                    .find(|tool| normalize_tool_name(tool) == normalize_tool_name(name))
+                    .cloned()
+                    .ok_or_else(|| ToolCallError::NotFound(name.to_owned()))
+            })
+            .collect()
+    }
+}
+
+impl<D> ToolSet<ToolObject<D>> {
+    fn insert_tool(
+        &mut self,
+        tool_object: ToolObject<D>,
+        schema: RootSchema,
+    ) -> Result<(), ToolSetCreationError> {
+        if self.tools.contains_key(&tool_object.name) {
+            return Err(ToolSetCreationError::NameConflict(tool_object.name.clone()));
+        }
+        let tool_object = if self.strict {
+            let name = tool_object.name.clone();
+            tool_object
+                .into_strict()
+                .map_err(|reason| ToolSetCreationError::NotStrict(name, reason))?
+        } else {
+            tool_object
+        };
+        self.tools.insert(tool_object.name.clone(), tool_object);
+        self.schemas.push(schema);
+        Ok(())
+    }
+
+    /// Marks tools that must not run alongside other calls, such as ones that
+    /// prompt the user. Names match as in `select`.
+    pub fn sequential<S: AsRef<str>>(mut self, names: &[S]) -> Result<Self, ToolCallError> {
+        for name in self.find_tools(names)? {
+            if let Some(tool) = self.tools.get_mut(&name) {
+                tool.sequential = true;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Fails calls to the named tools that run longer than `timeout`.
+    pub fn timeout<S: AsRef<str>>(
+        mut self,
+        names: &[S],
+        timeout: Duration,
+    ) -> Result<Self, ToolCallError> {
+        for name in self.find_tools(names)? {
+            if let Some(tool) = self.tools.get_mut(&name) {
+                tool.timeout = Some(timeout);
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn is_sequential(&self, name: &str) -> bool {
+        self.tools.get(name).is_some_and(|tool| tool.sequential)
+    }
+
+    /// Marks tools that change something, such as files, for approvers.
+    /// Names match as in `select`.
+    pub fn mutating<S: AsRef<str>>(mut self, names: &[S]) -> Result<Self, ToolCallError> {
+        for name in self.find_tools(names)? {
+            if let Some(tool) = self.tools.get_mut(&name) {
+                tool.mutating = true;
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn is_mutating(&self, name: &str) -> bool {
+        self.tools.get(name).is_some_and(|tool| tool.mutating)
+    }
+
+    /// Removes a tool, returning whether it was present.
+    pub fn remove_tool(&mut self, name: &str) -> bool {
+        self.schemas.retain(|schema| {
+            schema
+                .schema
+                .metadata
+                .as_deref()
+                .and_then(|metadata| metadata.title.as_deref())
+                != Some(name)
+        });
+        self.tools.remove(name).is_some()
+    }
+}
+
+const NO_APPROVER: &str = "no approver is set up to allow changes";
+
+fn normalize_tool_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
 }
 
 impl<C> ToolSet<SyncToolObject<C>> {
@@ -32,30 +188,26 @@ impl<C> ToolSet<SyncToolObject<C>> {
     {
         let tool_object =
             SyncToolObject::try_from_tool::<T>().map_err(ToolSetCreationError::Validation)?;
-        if self.tools.contains_key(&tool_object.name) {
-            Err(ToolSetCreationError::NameConflict(tool_object.name.clone()))
-        } else {
-            self.tools.insert(tool_object.name.clone(), tool_object);
-            let schema = schema_for!(T);
-            self.schemas.push(schema);
-            Ok(self)
-        }
+        self.insert_tool(tool_object, schema_for!(T))?;
+        Ok(self)
     }
 
+    /// Sync toolsets have no approver, so calls to mutating tools are
+    /// denied; convert with `into_async` and add one with `with_approver`.
     pub fn try_tool_call(
         &self,
         context: C,
         tool_name: &str,
         json: &str,
-    ) -> Result<String, ToolCallError> {
-        let tool = self
+    ) -> Result<Result<ToolOutput, anyhow::Error>, ToolCallError> {
+        let tool_object = self
             .tools
             .get(tool_name)
-            .ok_or_else(|| ToolCallError::NotFound(tool_name.to_owned()))
-            .and_then(|tool| {
-                tool.try_deserialize(json)
-                    .map_err(ToolCallError::Deserialization)
-            })?;
+            .ok_or_else(|| ToolCallError::NotFound(tool_name.to_owned()))?;
+        let tool = tool_object.try_deserialize(json)?;
+        if tool_object.mutating {
+            return Ok(Ok(ToolOutput::denied(NO_APPROVER)));
+        }
         Ok(tool.apply(context))
     }
 }
@@ -67,6 +219,8 @@ where
     pub fn into_async(self) -> AsyncToolSet<C> {
         AsyncToolSet {
             schemas: self.schemas,
+            strict: self.strict,
+            approver: self.approver,
             tools: self
                 .tools
                 .into_iter()
@@ -80,17 +234,100 @@ impl<C> ToolSet<AsyncToolObject<C>> {
     pub fn add_tool<T>(mut self) -> Result<Self, ToolSetCreationError>
     where
         T: JsonSchema + AsyncTool<Context = C> + for<'de> Deserialize<'de> + 'static,
+        C: 'static,
     {
         let tool_object =
             AsyncToolObject::try_from_tool::<T>().map_err(ToolSetCreationError::Validation)?;
-        if self.tools.contains_key(&tool_object.name) {
-            Err(ToolSetCreationError::NameConflict(tool_object.name.clone()))
-        } else {
-            self.tools.insert(tool_object.name.clone(), tool_object);
-            let schema = schema_for!(T);
-            self.schemas.push(schema);
-            Ok(self)
-        }
+        self.insert_tool(tool_object, schema_for!(T))?;
+        Ok(self)
+    }
+
+    /// Registers a tool that reports progress while it runs.
+    pub fn add_streaming_tool<T>(mut self) -> Result<Self, ToolSetCreationError>
+    where
+        T: JsonSchema
+            + StreamingTool<Context = C>
+            + Send
+            + Sync
+            + for<'de> Deserialize<'de>
+            + 'static,
+        C: Send + 'static,
+    {
+        let tool_object = AsyncToolObject::try_from_streaming_tool::<T>()
+            .map_err(ToolSetCreationError::Validation)?;
+        self.insert_tool(tool_object, schema_for!(T))?;
+        Ok(self)
+    }
+
+    /// Registers an async function as a tool. `A` describes the arguments as
+    /// with `add_tool`, except that the name and description are given here.
+    /// The function also gets the context, which its future may borrow, and a
+    /// token that fires when the call is cancelled or times out:
+    ///
+    /// ```ignore
+    /// toolset.add_fn("shout", "upper-case some text", async |args: Shout, ctx: &Log, _cancel| {
+    ///     ctx.record(&args.text).await;
+    ///     Ok(ToolOutput::ok(args.text.to_uppercase()))
+    /// })?;
+    /// ```
+    pub fn add_fn<A, F>(
+        mut self,
+        name: &str,
+        description: &str,
+        function: F,
+    ) -> Result<Self, ToolSetCreationError>
+    where
+        A: JsonSchema + DeserializeOwned + Send + 'static,
+        C: Send + 'static,
+        F: AsyncFn(A, &C, CancellationToken) -> Result<ToolOutput, anyhow::Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let schema = fn_schema::<A>(name, description);
+        let tool_object = AsyncToolObject::try_from_fn(&schema, function)
+            .map_err(ToolSetCreationError::Validation)?;
+        self.insert_tool(tool_object, schema)?;
+        Ok(self)
+    }
+
+    /// Registers a tool whose schema is only known at runtime.
+    pub fn add_dynamic(mut self, tool: DynamicTool<C>) -> Result<Self, ToolSetCreationError>
+    where
+        C: Send + 'static,
+    {
+        self.insert_dynamic(tool)?;
+        Ok(self)
+    }
+
+    /// Like `add_dynamic`, for a toolset that is already in use.
+    pub fn insert_dynamic(&mut self, tool: DynamicTool<C>) -> Result<(), ToolSetCreationError>
+    where
+        C: Send + 'static,
+    {
+        let mut schema =
+            serde_json::from_value::<RootSchema>(tool.schema.clone()).map_err(|err| {
+                ToolSetCreationError::Validation(ValidationError::JsonSerialization(err))
+            })?;
+        let metadata = schema.schema.metadata();
+        metadata.title = Some(tool.name.clone());
+        metadata.description = Some(tool.description.clone());
+        let tool_object =
+            AsyncToolObject::try_from_dynamic(tool).map_err(ToolSetCreationError::Validation)?;
+        self.insert_tool(tool_object, schema)
+    }
+
+    /// Asks `approver` before every call; denied calls return a denied output
+    /// without running. Without an approver, calls to mutating tools are
+    /// denied.
+    pub fn with_approver(mut self, approver: impl Approver + 'static) -> Self {
+        self.set_approver(approver);
+        self
+    }
+
+    /// Like `with_approver`, for a toolset that is already in use.
+    pub fn set_approver(&mut self, approver: impl Approver + 'static) {
+        self.approver = Some(Arc::new(approver));
     }
 
     pub async fn try_tool_call(
@@ -98,16 +335,73 @@ impl<C> ToolSet<AsyncToolObject<C>> {
         context: C,
         tool_name: &str,
         json: &str,
-    ) -> Result<Result<String, anyhow::Error>, ToolCallError> {
-        let tool = self
+    ) -> Result<Result<ToolOutput, anyhow::Error>, ToolCallError> {
+        self.try_tool_call_with_cancel(context, tool_name, json, CancellationToken::new())
+            .await
+    }
+
+    /// Like `try_tool_call`, but gives up as soon as `cancel` fires. Timeouts
+    /// and cancellation are reported as a [`ToolInterrupted`] error.
+    pub async fn try_tool_call_with_cancel(
+        &self,
+        context: C,
+        tool_name: &str,
+        json: &str,
+        cancel: CancellationToken,
+    ) -> Result<Result<ToolOutput, anyhow::Error>, ToolCallError> {
+        self.try_tool_call_with_progress(context, tool_name, json, cancel, Progress::default())
+            .await
+    }
+
+    /// Like `try_tool_call_with_cancel`; streaming tools report to `progress`.
+    pub async fn try_tool_call_with_progress(
+        &self,
+        context: C,
+        tool_name: &str,
+        json: &str,
+        cancel: CancellationToken,
+        progress: Progress,
+    ) -> Result<Result<ToolOutput, anyhow::Error>, ToolCallError> {
+        let tool_object = self
             .tools
             .get(tool_name)
-            .ok_or_else(|| ToolCallError::NotFound(tool_name.to_owned()))
-            .and_then(|tool| {
-                tool.try_deserialize(json)
-                    .map_err(ToolCallError::Deserialization)
-            })?;
-        Ok(tool.apply(context).await)
+            .ok_or_else(|| ToolCallError::NotFound(tool_name.to_owned()))?;
+        let tool = tool_object.try_deserialize_with_progress(json, progress)?;
+        // fires when the call is cancelled, times out or is dropped, for
+        // work the tool started outside of its future
+        let call_cancel = cancel.child_token();
+        let _cancel_on_drop = call_cancel.clone().drop_guard();
+        let run = async {
+            if self.approver.is_none() && tool_object.mutating {
+                return Ok(ToolOutput::denied(NO_APPROVER));
+            }
+            if let Some(approver) = &self.approver {
+                let request = ApprovalRequest {
+                    tool: tool_object.name.clone(),
+                    arguments: serde_json::from_str(json).unwrap_or_default(),
+                    mutating: tool_object.mutating,
+                };
+                match approver.approve(&request).await {
+                    Ok(Approval::Approved) => {}
+                    Ok(Approval::Denied(reason)) => return Ok(ToolOutput::denied(reason)),
+                    Err(err) => return Err(err.context("could not get approval")),
+                }
+            }
+            // the timeout starts once the call is approved
+            let apply = tool(context, call_cancel.clone());
+            match tool_object.timeout {
+                Some(limit) => tokio::time::timeout(limit, apply)
+                    .await
+                    .unwrap_or_else(|_| Err(ToolInterrupted::TimedOut(limit).into())),
+                None => apply.await,
+            }
+        };
+        // checked first, so calls queued after a cancellation never start
+        Ok(tokio::select! {
+            biased;
+            () = cancel.cancelled() => Err(ToolInterrupted::Cancelled.into()),
+            result = run => result,
+        })
     }
 }
 
@@ -119,3 +413,12 @@ where
         self.tools.values().map(ChatCompletionTool::from).collect()
     }
 }
+
+impl<T> ToolSet<T>
+where
+    ToolDefinition: for<'a> From<&'a T>,
+{
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.values().map(ToolDefinition::from).collect()
+    }
+}
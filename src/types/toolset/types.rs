@@ -1,4 +1,8 @@
 use super::tool_object::ValidationError;
+use crate::types::ToolOutput;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -7,12 +11,70 @@ pub enum ToolSetCreationError {
     Validation(ValidationError),
     #[error("two or more tools have the same name")]
     NameConflict(String),
+    #[error("tool '{0}' cannot be used in strict mode: {1}")]
+    NotStrict(String, String),
 }
 
 #[derive(Debug, Error)]
 pub enum ToolCallError {
-    #[error("error deserializing tool call (possible hallucination)")]
-    Deserialization(serde_json::Error),
-    #[error("tool not in toolset")]
+    /// `path` points at the offending argument, e.g. `paths[0]`.
+    #[error("error deserializing tool call at `{path}` (possible hallucination)")]
+    Deserialization {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("tool '{0}' not in toolset")]
     NotFound(String),
+    #[error("tool arguments do not match the schema: {}", display_violations(.0))]
+    InvalidArguments(Vec<ArgumentViolation>),
+}
+
+impl ToolCallError {
+    /// The error result a model gets for a call to `tool` that failed before
+    /// running, quoting the `arguments` it sent.
+    pub fn to_output(&self, tool: &str, arguments: &str) -> ToolOutput {
+        match self {
+            ToolCallError::NotFound(name) => ToolOutput::error(format!(
+                "there is no tool named '{name}' (called with {arguments})"
+            )),
+            ToolCallError::Deserialization { path, source } => ToolOutput::error(format!(
+                "invalid arguments for '{tool}' at `{path}`: {source}; arguments were {arguments}"
+            )),
+            ToolCallError::InvalidArguments(violations) => {
+                let list = violations
+                    .iter()
+                    .map(|violation| format!("\n- {violation}"))
+                    .collect::<String>();
+                ToolOutput::error(format!(
+                    "arguments for '{tool}' do not match its schema:{list}\narguments were {arguments}"
+                ))
+                .with_data(json!({ "violations": violations }))
+            }
+        }
+    }
+}
+
+/// One schema violation, addressed by a JSON pointer into the arguments.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArgumentViolation {
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for ArgumentViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pointer.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.pointer, self.message)
+        }
+    }
+}
+
+fn display_violations(violations: &[ArgumentViolation]) -> String {
+    violations
+        .iter()
+        .map(ArgumentViolation::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
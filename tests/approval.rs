@@ -0,0 +1,287 @@
+mod common;
+
+use common::TempDir;
+use hex::approval::{PolicyApprover, Unattended};
+use hex::client::web::WebApprover;
+use hex::config::ApprovalPolicy;
+use hex::offline_tools::offline_toolset;
+use hex::types::{Approval, ApprovalRequest, Approver, NoContext, ToolStatus};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Answers every question the same way and records which tools it was asked about.
+#[derive(Clone)]
+struct Asker {
+    answer: Approval,
+    asked: Arc<Mutex<Vec<String>>>,
+}
+
+impl Asker {
+    fn new(answer: Approval) -> Self {
+        Self {
+            answer,
+            asked: Arc::default(),
+        }
+    }
+
+    fn asked(&self) -> Vec<String> {
+        self.asked.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Approver for Asker {
+    async fn approve(&self, request: &ApprovalRequest) -> Result<Approval> {
+        self.asked.lock().unwrap().push(request.tool.clone());
+        Ok(self.answer.clone())
+    }
+}
+
+fn request(tool: &str, arguments: Value, mutating: bool) -> ApprovalRequest {
+    ApprovalRequest {
+        tool: tool.to_string(),
+        arguments,
+        mutating,
+    }
+}
+
+#[tokio::test]
+async fn policies_decide_reads_and_changes() {
+    let read = request("read_files", json!({ "paths": ["a.txt"] }), false);
+    let edit = request("edit_file", json!({ "path": "a.txt" }), true);
+    let denied = Approval::Denied("no".to_string());
+
+    let asker = Asker::new(denied.clone());
+    let ask = PolicyApprover::new(ApprovalPolicy::Ask, asker.clone());
+    assert_eq!(ask.approve(&read).await.unwrap(), denied);
+    assert_eq!(ask.approve(&edit).await.unwrap(), denied);
+    assert_eq!(asker.asked(), vec!["read_files", "edit_file"]);
+
+    let asker = Asker::new(denied.clone());
+    let reads = PolicyApprover::new(ApprovalPolicy::AutoApproveReads, asker.clone());
+    assert_eq!(reads.approve(&read).await.unwrap(), Approval::Approved);
+    assert_eq!(reads.approve(&edit).await.unwrap(), denied);
+    assert_eq!(asker.asked(), vec!["edit_file"]);
+
+    let asker = Asker::new(Approval::Approved);
+    let deny_all = PolicyApprover::new(ApprovalPolicy::DenyAll, asker.clone())
+        .with_allowed_paths(&["*.txt"])
+        .unwrap();
+    assert_eq!(deny_all.approve(&read).await.unwrap(), Approval::Approved);
+    assert!(matches!(
+        deny_all.approve(&edit).await.unwrap(),
+        Approval::Denied(_)
+    ));
+    assert!(asker.asked().is_empty());
+}
+
+#[tokio::test]
+async fn allowed_path_globs_skip_the_question() {
+    let asker = Asker::new(Approval::Denied("no".to_string()));
+    let approver = PolicyApprover::new(ApprovalPolicy::Ask, asker.clone())
+        .with_allowed_paths(&["src/**", "*.md"])
+        .unwrap();
+    let edit = |path: &str| request("edit_file", json!({ "path": path }), true);
+
+    for path in ["src/main.rs", "./src/a/b.rs", "README.md"] {
+        assert_eq!(
+            approver.approve(&edit(path)).await.unwrap(),
+            Approval::Approved,
+            "{path}"
+        );
+    }
+    for path in ["docs/guide.md", "src/../secret.txt", "/src/main.rs"] {
+        assert_ne!(
+            approver.approve(&edit(path)).await.unwrap(),
+            Approval::Approved,
+            "{path}"
+        );
+    }
+    let mixed = request(
+        "read_files",
+        json!({ "paths": ["src/lib.rs", "Cargo.toml"] }),
+        false,
+    );
+    assert_ne!(approver.approve(&mixed).await.unwrap(), Approval::Approved);
+    assert_eq!(asker.asked().len(), 4);
+
+    assert!(
+        PolicyApprover::new(ApprovalPolicy::Ask, asker)
+            .with_allowed_paths(&["src/[**"])
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn allowed_path_globs_never_reach_outside_the_working_directory() {
+    let asker = Asker::new(Approval::Denied("no".to_string()));
+    let edit = |path: &str| request("edit_file", json!({ "path": path }), true);
+    let everything = PolicyApprover::new(ApprovalPolicy::Ask, asker.clone())
+        .with_allowed_paths(&["**"])
+        .unwrap();
+    let markdown = PolicyApprover::new(ApprovalPolicy::Ask, asker.clone())
+        .with_allowed_paths(&["**/*.md"])
+        .unwrap();
+
+    assert_ne!(
+        everything.approve(&edit("/etc/passwd")).await.unwrap(),
+        Approval::Approved
+    );
+    assert_ne!(
+        markdown.approve(&edit("/tmp/x.md")).await.unwrap(),
+        Approval::Approved
+    );
+    let inside = std::env::current_dir().unwrap().join("README.md");
+    assert_eq!(
+        markdown
+            .approve(&edit(inside.to_str().unwrap()))
+            .await
+            .unwrap(),
+        Approval::Approved
+    );
+    assert_eq!(asker.asked().len(), 2);
+}
+
+#[tokio::test]
+async fn toolsets_only_run_approved_calls() {
+    let dir = TempDir::new("approval-toolset");
+    let path = dir.path().join("notes.txt");
+    std::fs::write(&path, "before").unwrap();
+    let arguments = json!({ "path": path, "contents": "after" }).to_string();
+
+    let asker = Asker::new(Approval::Denied("the user declined".to_string()));
+    let toolset = offline_toolset()
+        .into_async()
+        .with_approver(PolicyApprover::new(
+            ApprovalPolicy::AutoApproveReads,
+            asker.clone(),
+        ));
+    let output = toolset
+        .try_tool_call(NoContext(), "edit_file", &arguments)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.status, ToolStatus::Denied);
+    assert_eq!(output.text, "the user declined");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "before");
+
+    let listing = toolset
+        .try_tool_call(
+            NoContext(),
+            "list_directory",
+            &json!({ "path": dir.path() }).to_string(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(listing.is_ok());
+    assert_eq!(asker.asked(), vec!["edit_file"]);
+
+    let toolset = offline_toolset()
+        .into_async()
+        .with_approver(Asker::new(Approval::Approved));
+    let output = toolset
+        .try_tool_call(NoContext(), "edit_file", &arguments)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(output.is_ok());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "after");
+}
+
+#[tokio::test]
+async fn web_approvers_wait_for_the_app() {
+    let dir = TempDir::new("approval-web");
+    let path = dir.path().join("new.txt");
+    let (approver, mut requests) = WebApprover::new();
+    let toolset = offline_toolset().into_async().with_approver(approver);
+    let arguments = json!({ "path": path, "contents": "hi" }).to_string();
+
+    let call = toolset.try_tool_call(NoContext(), "create_file", &arguments);
+    let answer = async {
+        let pending = requests.recv().await.unwrap();
+        assert_eq!(pending.request.tool, "create_file");
+        assert!(pending.request.mutating);
+        assert_eq!(pending.request.paths(), vec![path.to_str().unwrap()]);
+        pending.approve();
+    };
+    let (output, ()) = tokio::join!(call, answer);
+    assert!(output.unwrap().unwrap().is_ok());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hi");
+
+    let call = toolset.try_tool_call(NoContext(), "create_file", &arguments);
+    let dismiss = async { drop(requests.recv().await) };
+    let (output, ()) = tokio::join!(call, dismiss);
+    assert_eq!(output.unwrap().unwrap().status, ToolStatus::Denied);
+
+    drop(requests);
+    let output = toolset
+        .try_tool_call(NoContext(), "create_file", &arguments)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.status, ToolStatus::Denied);
+}
+
+#[tokio::test]
+async fn toolsets_without_an_approver_deny_changes() {
+    let dir = TempDir::new("approval-none");
+    let path = dir.path().join("notes.txt");
+    std::fs::write(&path, "before").unwrap();
+    let arguments = json!({ "path": path, "contents": "after" }).to_string();
+
+    let output = offline_toolset()
+        .into_async()
+        .try_tool_call(NoContext(), "edit_file", &arguments)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.status, ToolStatus::Denied);
+
+    let output = offline_toolset()
+        .try_tool_call(NoContext(), "edit_file", &arguments)
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.status, ToolStatus::Denied);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "before");
+
+    let listing = offline_toolset()
+        .into_async()
+        .try_tool_call(
+            NoContext(),
+            "list_directory",
+            &json!({ "path": dir.path() }).to_string(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(listing.is_ok());
+}
+
+#[tokio::test]
+async fn unattended_approvers_only_allow_what_the_policy_does() {
+    // allowed globs only match inside the working directory
+    let dir = TempDir::inside(Path::new("target"), "approval-unattended");
+    let allowed = dir.path().join("allowed.txt");
+    let other = dir.path().join("other.txt");
+    let approver = PolicyApprover::new(ApprovalPolicy::AutoApproveReads, Unattended)
+        .with_allowed_paths(&[format!("{}/allowed.*", dir.path().display())])
+        .unwrap();
+    let toolset = offline_toolset().into_async().with_approver(approver);
+
+    for (path, status) in [(&allowed, ToolStatus::Ok), (&other, ToolStatus::Denied)] {
+        let arguments = json!({ "path": path, "contents": "hi" }).to_string();
+        let output = toolset
+            .try_tool_call(NoContext(), "create_file", &arguments)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.status, status, "{}", output.text);
+    }
+    assert!(allowed.exists());
+    assert!(!other.exists());
+}
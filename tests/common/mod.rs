@@ -0,0 +1,46 @@
+//! Helpers shared by the integration tests. Each test crate uses a different
+//! subset of them.
+#![allow(dead_code)]
+
+use hex::types::{Approval, ApprovalRequest, Approver};
+
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+/// Approves every call, for tests about what happens once a call runs.
+pub struct ApproveAll;
+
+#[async_trait]
+impl Approver for ApproveAll {
+    async fn approve(&self, _: &ApprovalRequest) -> anyhow::Result<Approval> {
+        Ok(Approval::Approved)
+    }
+}
+
+/// A fresh directory under the system temp dir, removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        Self::inside(&std::env::temp_dir(), name)
+    }
+
+    /// Like `new`, but under `parent`, e.g. a relative path for tests that
+    /// need the directory inside the working directory.
+    pub fn inside(parent: &Path, name: &str) -> Self {
+        let path = parent.join(format!("hex-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
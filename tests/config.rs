@@ -0,0 +1,224 @@
+use hex::config::{ApprovalPolicy, Config, ConfigLayer, Source, project_config_path};
+use hex::provider::ProviderKind;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+#[test]
+fn parses_every_setting_from_toml() {
+    let layer = ConfigLayer::from_toml(
+        r#"
+        provider = "anthropic"
+        model = "claude-sonnet-4-20250514"
+        api_base = "http://localhost:8080/v1"
+        temperature = 0.2
+        max_tokens = 512
+        tools = ["read_files", "list_directory"]
+        approval = "auto-approve-reads"
+        system_prompt = "be brief"
+        max_recursions = 4
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(layer.provider, Some(ProviderKind::Anthropic));
+    assert_eq!(layer.temperature, Some(0.2));
+    assert_eq!(layer.approval, Some(ApprovalPolicy::AutoApproveReads));
+    assert_eq!(
+        layer.tools,
+        Some(vec!["read_files".to_string(), "list_directory".to_string()])
+    );
+    assert_eq!(layer.max_recursions, Some(4));
+}
+
+#[test]
+fn parses_mcp_servers() {
+    let layer = ConfigLayer::from_toml(
+        r#"
+        [mcp_servers.files]
+        command = "mcp-files"
+        args = ["--root", "."]
+
+        [mcp_servers.search]
+        url = "http://localhost:9000/mcp"
+        headers = { Authorization = "Bearer token" }
+        "#,
+    )
+    .unwrap();
+
+    let servers = layer.mcp_servers.unwrap();
+    assert_eq!(servers["files"].command.as_deref(), Some("mcp-files"));
+    assert_eq!(servers["files"].args, vec!["--root", "."]);
+    assert_eq!(
+        servers["search"].url.as_deref(),
+        Some("http://localhost:9000/mcp")
+    );
+    assert_eq!(servers["search"].headers["Authorization"], "Bearer token");
+}
+
+#[test]
+fn parses_plugins() {
+    let layer = ConfigLayer::from_toml(
+        r#"
+        [plugins.jira]
+        command = "python3"
+        args = ["tools/jira.py"]
+        timeout_secs = 10
+        "#,
+    )
+    .unwrap();
+
+    let plugins = layer.plugins.unwrap();
+    assert_eq!(plugins["jira"].command, "python3");
+    assert_eq!(plugins["jira"].timeout_secs, Some(10));
+    assert!(ConfigLayer::from_toml("[plugins.jira]\nargs = []").is_err());
+}
+
+#[test]
+fn rejects_unknown_keys() {
+    assert!(ConfigLayer::from_toml("modle = \"gpt-4.1\"").is_err());
+}
+
+#[test]
+fn reads_prefixed_environment_variables() {
+    let env = HashMap::from([
+        ("HEX_MODEL", "llama3"),
+        ("HEX_TOOLS", "read_files, list_directory"),
+        ("HEX_MAX_RECURSIONS", "3"),
+        ("HEX_APPROVAL", "deny-all"),
+        ("HEX_ALLOW_PATHS", "src/**, docs/*.md"),
+        ("HEX_SYSTEM_PROMPT", ""),
+        ("HEX_API_KEY", "secret"),
+        ("HEX_HEADERS", "X-Team=hex, X-Trace = on"),
+    ]);
+    let layer = ConfigLayer::from_env(|name| env.get(name).map(|value| value.to_string())).unwrap();
+
+    assert_eq!(layer.model.as_deref(), Some("llama3"));
+    assert_eq!(
+        layer.tools,
+        Some(vec!["read_files".to_string(), "list_directory".to_string()])
+    );
+    assert_eq!(layer.max_recursions, Some(3));
+    assert_eq!(layer.approval, Some(ApprovalPolicy::DenyAll));
+    assert_eq!(
+        layer.allow_paths,
+        Some(vec!["src/**".to_string(), "docs/*.md".to_string()])
+    );
+    assert_eq!(layer.system_prompt, None);
+    assert_eq!(layer.api_key.as_deref(), Some("secret"));
+    assert_eq!(
+        layer.headers,
+        Some(BTreeMap::from([
+            ("X-Team".to_string(), "hex".to_string()),
+            ("X-Trace".to_string(), "on".to_string()),
+        ]))
+    );
+}
+
+#[test]
+fn rejects_malformed_environment_variables() {
+    let lookup = |name: &str| (name == "HEX_MAX_RECURSIONS").then(|| "many".to_string());
+    assert!(ConfigLayer::from_env(lookup).is_err());
+    let lookup = |name: &str| (name == "HEX_APPROVAL").then(|| "sometimes".to_string());
+    assert!(ConfigLayer::from_env(lookup).is_err());
+    let lookup = |name: &str| (name == "HEX_HEADERS").then(|| "X-Team".to_string());
+    assert!(ConfigLayer::from_env(lookup).is_err());
+}
+
+#[test]
+fn later_layers_override_earlier_ones_and_record_their_source() {
+    let user = PathBuf::from("/home/me/.config/hex/config.toml");
+    let project = PathBuf::from("/work/.hex.toml");
+    let config = Config::resolve([
+        (
+            Source::File(user.clone()),
+            ConfigLayer::from_toml("model = \"gpt-4.1-mini\"\nmax_recursions = 5").unwrap(),
+        ),
+        (
+            Source::File(project.clone()),
+            ConfigLayer::from_toml("model = \"gpt-4.1\"\nsystem_prompt = \"project\"").unwrap(),
+        ),
+        (
+            Source::Env,
+            ConfigLayer {
+                system_prompt: Some("env".to_string()),
+                ..Default::default()
+            },
+        ),
+        (
+            Source::Flag,
+            ConfigLayer {
+                provider: Some(ProviderKind::Anthropic),
+                ..Default::default()
+            },
+        ),
+    ]);
+
+    assert_eq!(config.provider, ProviderKind::Anthropic);
+    assert_eq!(config.source("provider"), &Source::Flag);
+    assert_eq!(config.model.as_deref(), Some("gpt-4.1"));
+    assert_eq!(config.source("model"), &Source::File(project));
+    assert_eq!(config.max_recursions, 5);
+    assert_eq!(config.source("max_recursions"), &Source::File(user));
+    assert_eq!(config.system_prompt.as_deref(), Some("env"));
+    assert_eq!(config.source("system_prompt"), &Source::Env);
+    assert_eq!(config.approval, ApprovalPolicy::AutoApproveReads);
+    assert_eq!(config.source("approval"), &Source::Default);
+}
+
+#[test]
+fn finds_the_nearest_project_config() {
+    let root = std::env::temp_dir().join(format!("hex-config-{}", std::process::id()));
+    let nested = root.join("a").join("b");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(root.join(".hex.toml"), "").unwrap();
+
+    assert_eq!(project_config_path(&nested), Some(root.join(".hex.toml")));
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn project_configs_cannot_add_plugins() {
+    let dir = std::env::temp_dir().join(format!("hex-config-plugins-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(".hex.toml");
+    std::fs::write(
+        &path,
+        "model = \"gpt-4.1\"\n[plugins.evil]\ncommand = \"sh\"\n",
+    )
+    .unwrap();
+
+    let err = ConfigLayer::from_project_file(&path).unwrap_err();
+    assert!(err.to_string().contains("may not set plugins;"), "{err}");
+    assert!(ConfigLayer::from_file(&path).unwrap().plugins.is_some());
+    std::fs::write(&path, "model = \"gpt-4.1\"\n").unwrap();
+    assert!(ConfigLayer::from_project_file(&path).is_ok());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn project_configs_cannot_start_servers_redirect_keys_or_loosen_approval() {
+    let dir = std::env::temp_dir().join(format!("hex-config-untrusted-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(".hex.toml");
+
+    for (setting, toml) in [
+        ("mcp_servers", "[mcp_servers.evil]\ncommand = \"sh\""),
+        ("api_base", "api_base = \"http://evil.example/v1\""),
+        ("approval", "approval = \"auto-approve-reads\""),
+        ("allow_paths", "allow_paths = [\"**\"]"),
+    ] {
+        std::fs::write(&path, toml).unwrap();
+        let err = ConfigLayer::from_project_file(&path).unwrap_err();
+        assert!(
+            err.to_string().contains(&format!("may not set {setting};")),
+            "{err}"
+        );
+    }
+    std::fs::write(&path, "model = \"gpt-4.1\"\ntools = [\"read_files\"]\n").unwrap();
+    assert!(ConfigLayer::from_project_file(&path).is_ok());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,762 @@
+use hex::client::web::WebClient;
+use hex::conversation::{Conversation, StreamPart, ToolProgress};
+use hex::offline_tools::offline_toolset;
+use hex::provider::{Message, MockChunk, MockProvider, ToolCall};
+use hex::types::{
+    AsyncTool, AsyncToolSet, CancellationToken, NoContext, Progress, ProgressEvent, StreamingTool,
+    ToolOutput, ToolSetCreationError, ToolStatus,
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+
+type Recorder = Arc<Mutex<Vec<String>>>;
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "record a value")]
+struct Record {
+    #[schemars(description = "the value to record")]
+    value: String,
+}
+
+#[async_trait]
+impl AsyncTool for Record {
+    type Context = Recorder;
+    async fn apply(&self, context: Self::Context, _: CancellationToken) -> Result<ToolOutput> {
+        context.lock().unwrap().push(self.value.clone());
+        Ok(format!("recorded {}", self.value).into())
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "always fails")]
+struct Fail {
+    #[schemars(description = "the file to blame")]
+    path: String,
+}
+
+#[async_trait]
+impl AsyncTool for Fail {
+    type Context = Recorder;
+    async fn apply(&self, _: Self::Context, _: CancellationToken) -> Result<ToolOutput> {
+        Err(anyhow::anyhow!("permission denied"))
+            .with_context(|| format!("could not open {}", self.path))
+    }
+}
+
+fn record_toolset() -> AsyncToolSet<Recorder> {
+    AsyncToolSet::new().add_tool::<Record>().unwrap()
+}
+
+fn failing_turns() -> MockProvider {
+    MockProvider::from_json(
+        r#"[
+          [{ "tool_calls": [{ "index": 0, "id": "call", "function": { "name": "Fail", "arguments": "{\"path\": \"/etc/secret\"}" } }], "finish_reason": "tool_calls" }],
+          [{ "content": "it failed" }]
+        ]"#,
+    )
+    .unwrap()
+}
+
+fn tool_message(conversation: &Conversation<Recorder>) -> &str {
+    conversation
+        .messages()
+        .iter()
+        .find_map(|message| match message {
+            Message::Tool { content, .. } => Some(content.as_str()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn content(text: &str) -> MockChunk {
+    MockChunk {
+        content: Some(text.to_string()),
+        ..Default::default()
+    }
+}
+
+fn tool_call(id: &str, name: &str, json: &str) -> ToolCall {
+    ToolCall {
+        id: id.to_string(),
+        name: name.to_string(),
+        json: json.to_string(),
+    }
+}
+
+async fn collect<T: Clone>(
+    conversation: &mut Conversation<T>,
+    message: &str,
+) -> Vec<Result<StreamPart>> {
+    conversation
+        .send_message(message.to_string())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn streams_content_chunks_in_order() {
+    let provider = MockProvider::new(vec![vec![content("hel"), content("lo")]]);
+    let requests = provider.requests();
+    let mut conversation =
+        Conversation::new(Box::new(provider), record_toolset(), Recorder::default());
+
+    let parts = collect(&mut conversation, "hi").await;
+
+    let parts = parts.into_iter().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(
+        parts,
+        vec![
+            StreamPart::Content("hel".to_string()),
+            StreamPart::Content("lo".to_string())
+        ]
+    );
+    assert_eq!(requests.lock().unwrap().len(), 1);
+    assert_eq!(
+        conversation.messages(),
+        &[
+            Message::User {
+                content: "hi".to_string()
+            },
+            Message::Assistant {
+                content: Some("hello".to_string()),
+                tool_calls: vec![]
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn assembles_fragmented_tool_calls_and_dispatches_them() {
+    let provider =
+        MockProvider::from_json(include_str!("fixtures/fragmented_tool_calls.json")).unwrap();
+    let requests = provider.requests();
+    let recorder = Recorder::default();
+    let mut conversation =
+        Conversation::new(Box::new(provider), record_toolset(), recorder.clone());
+
+    let parts = collect(&mut conversation, "record a and b").await;
+
+    let parts = parts.into_iter().collect::<Result<Vec<_>>>().unwrap();
+    let calls = parts
+        .iter()
+        .filter_map(|part| match part {
+            StreamPart::ToolCall(call) => Some(call.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![
+            tool_call("call_a", "Record", r#"{"value": "a"}"#),
+            tool_call("call_b", "Record", r#"{"value": "b"}"#),
+        ]
+    );
+    assert_eq!(*recorder.lock().unwrap(), vec!["a", "b"]);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].tools.len(), 1);
+    assert_eq!(requests[0].tools[0].name, "Record");
+    assert_eq!(
+        &requests[1].messages[1..],
+        &[
+            Message::Assistant {
+                content: Some("Let me record that.".to_string()),
+                tool_calls: calls,
+            },
+            Message::Tool {
+                tool_call_id: "call_a".to_string(),
+                content: "recorded a".to_string(),
+                status: ToolStatus::Ok,
+            },
+            Message::Tool {
+                tool_call_id: "call_b".to_string(),
+                content: "recorded b".to_string(),
+                status: ToolStatus::Ok,
+            },
+        ]
+    );
+    assert_eq!(
+        conversation.messages().last(),
+        Some(&Message::Assistant {
+            content: Some("Recorded both.".to_string()),
+            tool_calls: vec![]
+        })
+    );
+}
+
+#[tokio::test]
+async fn flushes_tool_calls_at_each_finish_reason() {
+    let provider =
+        MockProvider::from_json(include_str!("fixtures/interleaved_finish_reasons.json")).unwrap();
+    let recorder = Recorder::default();
+    let mut conversation =
+        Conversation::new(Box::new(provider), record_toolset(), recorder.clone());
+
+    let parts = collect(&mut conversation, "record").await;
+
+    assert!(parts.iter().all(Result::is_ok));
+    assert_eq!(*recorder.lock().unwrap(), vec!["a", "b"]);
+    let tool_results = conversation
+        .messages()
+        .iter()
+        .filter(|message| matches!(message, Message::Tool { .. }))
+        .count();
+    assert_eq!(tool_results, 2);
+}
+
+#[tokio::test]
+async fn stops_at_the_recursion_limit() {
+    let looping_turn = || {
+        serde_json::from_str::<Vec<MockChunk>>(
+            r#"[{ "tool_calls": [{ "index": 0, "id": "call", "function": { "name": "Record", "arguments": "{\"value\": \"x\"}" } }], "finish_reason": "tool_calls" }]"#,
+        )
+        .unwrap()
+    };
+    let provider = MockProvider::new((0..5).map(|_| looping_turn()).collect());
+    let requests = provider.requests();
+    let mut conversation =
+        Conversation::new(Box::new(provider), record_toolset(), Recorder::default())
+            .with_max_recursions(3);
+
+    let parts = collect(&mut conversation, "loop").await;
+
+    assert!(parts.last().unwrap().is_err());
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn dispatches_sync_offline_tools() {
+    let provider = MockProvider::from_json(
+        r#"[
+          [{ "tool_calls": [{ "index": 0, "id": "call", "function": { "name": "read_files", "arguments": "{\"paths\": [\"Cargo.toml\"]}" } }], "finish_reason": "tool_calls" }],
+          [{ "content": "done" }]
+        ]"#,
+    )
+    .unwrap();
+    let mut conversation = Conversation::new(
+        Box::new(provider),
+        offline_toolset().into_async(),
+        NoContext(),
+    );
+
+    collect(&mut conversation, "read the manifest").await;
+
+    let Message::Tool { content, .. } = &conversation.messages()[2] else {
+        panic!("expected a tool message");
+    };
+    assert!(content.starts_with("[Cargo.toml]\n[package]"));
+}
+
+#[tokio::test]
+async fn reports_failed_tool_results_to_client_and_model() {
+    let provider = MockProvider::from_json(
+        r#"[
+          [{ "tool_calls": [{ "index": 0, "id": "call", "function": { "name": "list_directory", "arguments": "{\"path\": \"does/not/exist\"}" } }], "finish_reason": "tool_calls" }],
+          [{ "content": "no such directory" }]
+        ]"#,
+    )
+    .unwrap();
+    let mut conversation = Conversation::new(
+        Box::new(provider),
+        offline_toolset().into_async(),
+        NoContext(),
+    );
+
+    let parts = collect(&mut conversation, "list it").await;
+
+    let result = parts
+        .iter()
+        .find_map(|part| match part {
+            Ok(StreamPart::ToolResult(result)) => Some(result),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(result.name, "list_directory");
+    assert_eq!(result.output.status, ToolStatus::Error);
+    let Message::Tool {
+        content, status, ..
+    } = &conversation.messages()[2]
+    else {
+        panic!("expected a tool message");
+    };
+    assert_eq!(*status, ToolStatus::Error);
+    assert!(content.starts_with("error: could not list does/not/exist"));
+}
+
+#[tokio::test]
+async fn feeds_tool_error_chain_back_to_the_model() {
+    let mut conversation = Conversation::new(
+        Box::new(failing_turns()),
+        AsyncToolSet::new().add_tool::<Fail>().unwrap(),
+        Recorder::default(),
+    );
+
+    collect(&mut conversation, "fail").await;
+
+    assert_eq!(
+        tool_message(&conversation),
+        "error: could not open /etc/secret: permission denied"
+    );
+}
+
+#[tokio::test]
+async fn redacts_tool_errors_when_asked() {
+    let mut conversation = Conversation::new(
+        Box::new(failing_turns()),
+        AsyncToolSet::new().add_tool::<Fail>().unwrap(),
+        Recorder::default(),
+    )
+    .with_error_redactor(|err| err.root_cause().to_string());
+
+    collect(&mut conversation, "fail").await;
+
+    assert_eq!(tool_message(&conversation), "error: permission denied");
+}
+
+#[tokio::test]
+async fn answers_unknown_tools_with_the_available_ones() {
+    let provider = MockProvider::from_json(
+        r#"[
+          [{ "tool_calls": [{ "index": 0, "id": "call", "function": { "name": "Delete", "arguments": "{}" } }], "finish_reason": "tool_calls" }],
+          [{ "tool_calls": [{ "index": 0, "id": "retry", "function": { "name": "Record", "arguments": "{\"value\": \"a\"}" } }], "finish_reason": "tool_calls" }],
+          [{ "content": "done" }]
+        ]"#,
+    )
+    .unwrap();
+    let requests = provider.requests();
+    let recorder = Recorder::default();
+    let mut conversation =
+        Conversation::new(Box::new(provider), record_toolset(), recorder.clone());
+
+    collect(&mut conversation, "delete").await;
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        &requests[1].messages[1..],
+        &[
+            Message::Assistant {
+                content: None,
+                tool_calls: vec![tool_call("call", "Delete", "{}")],
+            },
+            Message::Tool {
+                tool_call_id: "call".to_string(),
+                content: "error: there is no tool named 'Delete' (called with {}); available tools: Record"
+                    .to_string(),
+                status: ToolStatus::Error,
+            },
+        ]
+    );
+    assert_eq!(*recorder.lock().unwrap(), vec!["a"]);
+}
+
+#[tokio::test]
+async fn lists_every_schema_violation() {
+    let provider = MockProvider::from_json(
+        r#"[
+          [{ "tool_calls": [{ "index": 0, "id": "call", "function": { "name": "read_files", "arguments": "{\"paths\": [\"a\", 3, false]}" } }], "finish_reason": "tool_calls" }],
+          [{ "content": "sorry" }]
+        ]"#,
+    )
+    .unwrap();
+    let mut conversation = Conversation::new(
+        Box::new(provider),
+        offline_toolset().into_async(),
+        NoContext(),
+    );
+
+    let parts = collect(&mut conversation, "read").await;
+
+    let Message::Tool { content, .. } = &conversation.messages()[2] else {
+        panic!("expected a tool message");
+    };
+    assert_eq!(
+        content,
+        "error: arguments for 'read_files' do not match its schema:\n\
+         - /paths/1: 3 is not of type \"string\"\n\
+         - /paths/2: false is not of type \"string\"\n\
+         arguments were {\"paths\": [\"a\", 3, false]}"
+    );
+    let data = parts
+        .iter()
+        .find_map(|part| match part {
+            Ok(StreamPart::ToolResult(result)) => result.output.data.clone(),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(data["violations"][0]["pointer"], "/paths/1");
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct Shout {
+    #[schemars(description = "text to shout")]
+    text: String,
+}
+
+#[tokio::test]
+async fn dispatches_function_tools() {
+    let toolset = AsyncToolSet::new()
+        .add_fn(
+            "shout",
+            "upper-case some text",
+            async |args: Shout, recorder: &Recorder, _| {
+                recorder.lock().unwrap().push(args.text.clone());
+                Ok(ToolOutput::ok(args.text.to_uppercase()))
+            },
+        )
+        .unwrap();
+    let definition = &toolset.tool_definitions()[0];
+    assert_eq!(definition.name, "shout");
+    assert_eq!(definition.description, "upper-case some text");
+
+    let recorder = Recorder::default();
+    let output = toolset
+        .try_tool_call(recorder.clone(), "shout", r#"{"text": "hi"}"#)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(output.text, "HI");
+    assert_eq!(*recorder.lock().unwrap(), vec!["hi"]);
+}
+
+#[test]
+fn function_tools_share_add_tool_checks() {
+    let shout = async |args: Shout, _: &Recorder, _| Ok(ToolOutput::ok(args.text));
+
+    let conflict = record_toolset().add_fn("Record", "shadows Record", shout);
+    assert!(matches!(
+        conflict,
+        Err(ToolSetCreationError::NameConflict(name)) if name == "Record"
+    ));
+
+    #[derive(Deserialize, JsonSchema)]
+    struct Undescribed {
+        #[allow(dead_code)]
+        text: String,
+    }
+    let undescribed = AsyncToolSet::<Recorder>::new().add_fn(
+        "undescribed",
+        "arguments lack descriptions",
+        async |_: Undescribed, _, _| Ok(ToolOutput::ok("")),
+    );
+    assert!(matches!(
+        undescribed,
+        Err(ToolSetCreationError::Validation(_))
+    ));
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct Nap {
+    #[schemars(description = "label recorded when the nap ends")]
+    label: String,
+    #[schemars(description = "milliseconds to sleep")]
+    ms: u64,
+}
+
+/// A toolset whose `nap` records its label when done and counts how many
+/// naps overlap; `ask` is sequential and records when it runs.
+fn nap_toolset(peak: Arc<Mutex<(usize, usize)>>) -> AsyncToolSet<Recorder> {
+    AsyncToolSet::new()
+        .add_fn(
+            "nap",
+            "sleep, then record",
+            async move |args: Nap, recorder: &Recorder, _| {
+                {
+                    let mut peak = peak.lock().unwrap();
+                    peak.0 += 1;
+                    peak.1 = peak.1.max(peak.0);
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(args.ms)).await;
+                peak.lock().unwrap().0 -= 1;
+                recorder.lock().unwrap().push(args.label.clone());
+                Ok(ToolOutput::ok(args.label))
+            },
+        )
+        .unwrap()
+        .add_fn(
+            "ask",
+            "pretend to prompt the user",
+            async |args: Shout, recorder: &Recorder, _| {
+                recorder.lock().unwrap().push(args.text.clone());
+                Ok(ToolOutput::ok(args.text))
+            },
+        )
+        .unwrap()
+        .sequential(&["ask"])
+        .unwrap()
+}
+
+fn parallel_turns(calls: &[(&str, serde_json::Value)]) -> MockProvider {
+    let tool_calls = calls
+        .iter()
+        .enumerate()
+        .map(|(index, (name, arguments))| {
+            serde_json::json!({
+                "index": index,
+                "id": format!("call{index}"),
+                "function": { "name": name, "arguments": arguments.to_string() }
+            })
+        })
+        .collect::<Vec<_>>();
+    let turn = serde_json::json!([{ "tool_calls": tool_calls, "finish_reason": "tool_calls" }]);
+    MockProvider::new(vec![
+        serde_json::from_value(turn).unwrap(),
+        vec![content("done")],
+    ])
+}
+
+fn tool_call_ids<T: Clone>(conversation: &Conversation<T>) -> Vec<&str> {
+    conversation
+        .messages()
+        .iter()
+        .filter_map(|message| match message {
+            Message::Tool { tool_call_id, .. } => Some(tool_call_id.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn runs_tool_calls_concurrently_but_reports_them_in_order() {
+    let provider = parallel_turns(&[
+        ("nap", serde_json::json!({ "label": "slow", "ms": 200 })),
+        ("nap", serde_json::json!({ "label": "fast", "ms": 10 })),
+        ("nap", serde_json::json!({ "label": "medium", "ms": 60 })),
+    ]);
+    let peak = Arc::new(Mutex::new((0, 0)));
+    let recorder = Recorder::default();
+    let mut conversation = Conversation::new(
+        Box::new(provider),
+        nap_toolset(peak.clone()),
+        recorder.clone(),
+    )
+    .with_max_parallel_tools(2);
+
+    let parts = collect(&mut conversation, "nap").await;
+
+    assert!(parts.iter().all(Result::is_ok));
+    assert_eq!(peak.lock().unwrap().1, 2);
+    assert_eq!(*recorder.lock().unwrap(), vec!["fast", "medium", "slow"]);
+    assert_eq!(
+        tool_call_ids(&conversation),
+        vec!["call0", "call1", "call2"]
+    );
+    let results = parts
+        .iter()
+        .filter_map(|part| match part {
+            Ok(StreamPart::ToolResult(result)) => Some(result.output.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec!["slow", "fast", "medium"]);
+}
+
+#[tokio::test]
+async fn sequential_tools_wait_for_earlier_calls_and_run_alone() {
+    let provider = parallel_turns(&[
+        ("nap", serde_json::json!({ "label": "before", "ms": 50 })),
+        ("ask", serde_json::json!({ "text": "ask" })),
+        ("nap", serde_json::json!({ "label": "after", "ms": 1 })),
+    ]);
+    let peak = Arc::new(Mutex::new((0, 0)));
+    let recorder = Recorder::default();
+    let mut conversation = Conversation::new(
+        Box::new(provider),
+        nap_toolset(peak.clone()),
+        recorder.clone(),
+    );
+
+    collect(&mut conversation, "nap").await;
+
+    assert_eq!(*recorder.lock().unwrap(), vec!["before", "ask", "after"]);
+    assert_eq!(
+        tool_call_ids(&conversation),
+        vec!["call0", "call1", "call2"]
+    );
+}
+
+#[tokio::test]
+async fn reports_timed_out_tools_to_the_model() {
+    let provider = parallel_turns(&[
+        ("nap", serde_json::json!({ "label": "slow", "ms": 5000 })),
+        ("nap", serde_json::json!({ "label": "fast", "ms": 1 })),
+    ]);
+    let toolset = nap_toolset(Arc::default())
+        .timeout(&["nap"], std::time::Duration::from_millis(100))
+        .unwrap();
+    let recorder = Recorder::default();
+    let mut conversation = Conversation::new(Box::new(provider), toolset, recorder.clone());
+
+    let parts = collect(&mut conversation, "nap").await;
+
+    assert!(parts.iter().all(Result::is_ok));
+    assert_eq!(*recorder.lock().unwrap(), vec!["fast"]);
+    let outputs = conversation
+        .messages()
+        .iter()
+        .filter_map(|message| match message {
+            Message::Tool { content, .. } => Some(content.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(
+        outputs[0].contains("timed out after 100ms"),
+        "{}",
+        outputs[0]
+    );
+    assert!(!outputs[1].contains("timed out"), "{}", outputs[1]);
+}
+
+#[tokio::test]
+async fn cancels_running_tool_calls() {
+    let provider = parallel_turns(&[
+        ("nap", serde_json::json!({ "label": "first", "ms": 5000 })),
+        ("nap", serde_json::json!({ "label": "second", "ms": 5000 })),
+    ]);
+    let recorder = Recorder::default();
+    let mut conversation = Conversation::new(
+        Box::new(provider),
+        nap_toolset(Arc::default()),
+        recorder.clone(),
+    );
+    let canceller = conversation.tool_canceller();
+    assert!(!canceller.cancel());
+
+    let started = std::time::Instant::now();
+    let (parts, cancelled) = tokio::join!(collect(&mut conversation, "nap"), async {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        canceller.cancel()
+    });
+
+    assert!(cancelled);
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    assert!(parts.iter().all(Result::is_ok));
+    assert!(recorder.lock().unwrap().is_empty());
+    let results = parts
+        .iter()
+        .filter_map(|part| match part {
+            Ok(StreamPart::ToolResult(result)) => Some(&result.output),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|output| {
+        output.status == ToolStatus::Error && output.text.contains("cancelled by the user")
+    }));
+    assert!(!canceller.cancel());
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "build in steps, reporting progress")]
+struct Build {
+    #[schemars(description = "how many steps to run")]
+    steps: u32,
+}
+
+#[async_trait]
+impl StreamingTool for Build {
+    type Context = Recorder;
+    async fn apply(
+        &self,
+        _: Self::Context,
+        progress: Progress,
+        _: CancellationToken,
+    ) -> Result<ToolOutput> {
+        for step in 1..=self.steps {
+            progress.log(format!("step {step}"));
+            progress.percent(100.0 * step as f32 / self.steps as f32);
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        Ok(ToolOutput::ok("built"))
+    }
+}
+
+#[tokio::test]
+async fn streams_tool_progress_to_the_client_only() {
+    let provider = parallel_turns(&[("Build", serde_json::json!({ "steps": 2 }))]);
+    let toolset = AsyncToolSet::new().add_streaming_tool::<Build>().unwrap();
+    let mut conversation = Conversation::new(Box::new(provider), toolset, Recorder::default());
+
+    let parts = collect(&mut conversation, "build")
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .filter(|part| !matches!(part, StreamPart::Content(_)))
+        .collect::<Vec<_>>();
+
+    let progress = |event| {
+        StreamPart::ToolProgress(ToolProgress {
+            id: "call0".to_string(),
+            name: "Build".to_string(),
+            event,
+        })
+    };
+    assert_eq!(parts.len(), 6);
+    assert!(matches!(parts[0], StreamPart::ToolCall(_)));
+    assert_eq!(
+        parts[1..5],
+        [
+            progress(ProgressEvent::Log("step 1".to_string())),
+            progress(ProgressEvent::Percent(50.0)),
+            progress(ProgressEvent::Log("step 2".to_string())),
+            progress(ProgressEvent::Percent(100.0)),
+        ]
+    );
+    assert!(matches!(&parts[5], StreamPart::ToolResult(result) if result.output.text == "built"));
+    assert_eq!(tool_message(&conversation), "built");
+}
+
+#[tokio::test]
+async fn web_clients_send_the_system_prompt() {
+    let terse = Message::System {
+        content: "be terse".to_string(),
+    };
+
+    let provider = MockProvider::new(vec![vec![content("hi")]]);
+    let requests = provider.requests();
+    let conversation = Conversation::new(Box::new(provider), record_toolset(), Recorder::default())
+        .with_system_prompt("be terse");
+    let mut client = WebClient::from_conversation(conversation);
+    let stream = client.send_message("hello".to_string()).await.unwrap();
+    assert_eq!(stream.count().await, 1);
+    assert_eq!(requests.lock().unwrap()[0].messages[0], terse);
+
+    let provider = MockProvider::new(vec![vec![content("hi")]]);
+    let requests = provider.requests();
+    let mut client =
+        WebClient::with_provider(Box::new(provider), record_toolset(), Recorder::default())
+            .with_system_prompt("be terse");
+    let stream = client.send_message("hello".to_string()).await.unwrap();
+    assert_eq!(stream.count().await, 1);
+    assert_eq!(requests.lock().unwrap()[0].messages[0], terse);
+}
+
+#[tokio::test]
+async fn web_clients_limit_parallel_tools_and_recursions() {
+    let provider = parallel_turns(&[
+        ("nap", serde_json::json!({ "label": "first", "ms": 20 })),
+        ("nap", serde_json::json!({ "label": "second", "ms": 20 })),
+    ]);
+    let requests = provider.requests();
+    let peak = Arc::new(Mutex::new((0, 0)));
+    let mut client = WebClient::with_provider(
+        Box::new(provider),
+        nap_toolset(peak.clone()),
+        Recorder::default(),
+    )
+    .with_max_parallel_tools(1)
+    .with_max_recursions(1);
+
+    let stream = client.send_message("nap".to_string()).await.unwrap();
+    let parts = stream.collect::<Vec<_>>().await;
+
+    assert_eq!(peak.lock().unwrap().1, 1);
+    assert!(parts.last().unwrap().is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,94 @@
+use hex::types::{HexTool, NoContext, SyncToolSet, Tool, ToolOutput};
+
+use serde_json::json;
+
+/// Count the words in a text.
+///
+/// Whitespace separates words.
+#[derive(HexTool, Debug, PartialEq)]
+#[tool(name = "word_count")]
+#[serde(deny_unknown_fields)]
+struct WordCount {
+    /// the text to count
+    text: String,
+    #[serde(default, rename = "min_length")]
+    #[tool(description = "ignore words shorter than this")]
+    min: usize,
+}
+
+impl Tool for WordCount {
+    type Context = NoContext;
+    fn apply(&self, _: Self::Context) -> anyhow::Result<ToolOutput> {
+        let count = self
+            .text
+            .split_whitespace()
+            .filter(|word| word.len() >= self.min)
+            .count();
+        Ok(ToolOutput::ok(count.to_string()))
+    }
+}
+
+/// Takes no arguments
+#[derive(HexTool)]
+struct Ping;
+
+impl Tool for Ping {
+    type Context = NoContext;
+    fn apply(&self, _: Self::Context) -> anyhow::Result<ToolOutput> {
+        Ok(ToolOutput::ok("pong"))
+    }
+}
+
+#[test]
+fn derives_names_and_descriptions() {
+    let toolset = SyncToolSet::new()
+        .add_tool::<WordCount>()
+        .unwrap()
+        .add_tool::<Ping>()
+        .unwrap();
+
+    assert_eq!(toolset.tool_names(), vec!["Ping", "word_count"]);
+    let definition = toolset
+        .tool_definitions()
+        .into_iter()
+        .find(|definition| definition.name == "word_count")
+        .unwrap();
+    assert_eq!(
+        definition.description,
+        "Count the words in a text.\n\nWhitespace separates words."
+    );
+    assert_eq!(
+        definition.parameters["properties"]["text"]["description"],
+        "the text to count"
+    );
+    assert_eq!(
+        definition.parameters["properties"]["min_length"]["description"],
+        "ignore words shorter than this"
+    );
+    assert_eq!(definition.parameters["required"], json!(["text"]));
+    assert_eq!(definition.parameters["additionalProperties"], json!(false));
+}
+
+#[test]
+fn deserializes_through_forwarded_serde_attributes() {
+    let tool: WordCount = serde_json::from_str(r#"{"text": "a bb ccc", "min_length": 2}"#).unwrap();
+    assert_eq!(
+        tool,
+        WordCount {
+            text: "a bb ccc".to_string(),
+            min: 2
+        }
+    );
+
+    let toolset = SyncToolSet::new().add_tool::<WordCount>().unwrap();
+    let output = toolset
+        .try_tool_call(NoContext(), "word_count", r#"{"text": "a bb ccc"}"#)
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.text, "3");
+    assert!(
+        toolset
+            .try_tool_call(NoContext(), "word_count", r#"{"text": "", "extra": 1}"#)
+            .is_err()
+    );
+}
@@ -0,0 +1,15 @@
+#!/bin/sh
+# Test MCP server: records each start in $LAUNCHES, answers `initialize` and
+# `tools/list`, and exits with a message on stderr on every `tools/call`.
+echo started >> "$LAUNCHES"
+while IFS= read -r line; do
+    id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+    case "$line" in
+    *'"method":"initialize"'*) echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{}}" ;;
+    *'"method":"tools/list"'*) echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"tools\":[]}}" ;;
+    *'"method":"tools/call"'*)
+        echo "crashed on purpose" >&2
+        exit 1
+        ;;
+    esac
+done
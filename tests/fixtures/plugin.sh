@@ -0,0 +1,31 @@
+#!/bin/sh
+# Test plugin: `shout` is read-only and upper-cases its text, logging to
+# stderr, `nap` outlasts short timeouts and `crash` fails with a message on
+# stderr.
+case "$1" in
+manifest)
+    cat <<'JSON'
+{"tools": [
+  {"name": "shout", "description": "upper-case some text", "mutating": false,
+   "input_schema": {"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}},
+  {"name": "nap", "description": "sleep for a while", "input_schema": {"type": "object"}},
+  {"name": "crash", "description": "always fails", "input_schema": {"type": "object"}}
+]}
+JSON
+    ;;
+call)
+    input=$(cat)
+    case "$2" in
+    shout)
+        text=$(printf '%s' "$input" | sed -n 's/.*"text": *"\([^"]*\)".*/\1/p' | tr 'a-z' 'A-Z')
+        echo "shouting" >&2
+        printf '{"text": "%s", "data": {"length": %d}}\n' "$text" "${#text}"
+        ;;
+    nap) sleep 5 ;;
+    crash)
+        echo "something went wrong" >&2
+        exit 3
+        ;;
+    esac
+    ;;
+esac
@@ -0,0 +1,395 @@
+mod common;
+
+use common::ApproveAll;
+use hex::approval::PolicyApprover;
+use hex::config::{ApprovalPolicy, McpServerConfig};
+use hex::mcp::protocol::RpcMessage;
+use hex::mcp::{McpClient, McpServer, McpServers};
+use hex::types::{
+    AsyncToolSet, CancellationToken, NoContext, ToolInterrupted, ToolOutput, ToolStatus,
+};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+/// A minimal MCP server: `echo` is read-only and returns its text, `fail` reports a tool
+/// error, and `grow` adds a `count` tool and announces the change.
+async fn fake_server(io: DuplexStream) {
+    let (reader, mut writer) = tokio::io::split(io);
+    let mut lines = BufReader::new(reader).lines();
+    let mut tools = vec![json!({
+        "name": "echo",
+        "description": "echo some text",
+        "inputSchema": {
+            "type": "object",
+            "properties": { "text": { "type": "string" } },
+            "required": ["text"]
+        },
+        "annotations": { "readOnlyHint": true }
+    })];
+    let empty = json!({ "type": "object", "properties": {} });
+    for name in ["fail", "grow"] {
+        tools.push(json!({ "name": name, "description": name, "inputSchema": empty }));
+    }
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request: RpcMessage = serde_json::from_str(&line).unwrap();
+        let (Some(id), Some(method)) = (request.id, request.method) else {
+            continue;
+        };
+        let params = request.params.unwrap_or_default();
+        let result = match method.as_str() {
+            "initialize" => json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": { "listChanged": true } },
+                "serverInfo": { "name": "fake", "version": "0" }
+            }),
+            // one tool per page to exercise pagination
+            "tools/list" => {
+                let page = params["cursor"]
+                    .as_str()
+                    .map_or(0, |cursor| cursor.parse().unwrap());
+                let next = (page + 1 < tools.len()).then(|| (page + 1).to_string());
+                json!({ "tools": [tools[page]], "nextCursor": next })
+            }
+            "tools/call" => match params["name"].as_str().unwrap() {
+                "echo" => json!({
+                    "content": [{ "type": "text", "text": params["arguments"]["text"] }]
+                }),
+                "fail" => json!({
+                    "content": [{ "type": "text", "text": "it broke" }],
+                    "isError": true
+                }),
+                _ => {
+                    tools.push(
+                        json!({ "name": "count", "description": "count", "inputSchema": empty }),
+                    );
+                    let changed =
+                        RpcMessage::notification("notifications/tools/list_changed", Value::Null);
+                    let line = serde_json::to_string(&changed).unwrap() + "\n";
+                    writer.write_all(line.as_bytes()).await.unwrap();
+                    json!({ "content": [] })
+                }
+            },
+            _ => continue,
+        };
+        let line = serde_json::to_string(&RpcMessage::response(id, result)).unwrap() + "\n";
+        writer.write_all(line.as_bytes()).await.unwrap();
+    }
+}
+
+async fn connect() -> McpServers {
+    let (client_io, server_io) = tokio::io::duplex(4096);
+    tokio::spawn(fake_server(server_io));
+    let (reader, writer) = tokio::io::split(client_io);
+    let client = McpClient::from_io("fake", reader, writer).await.unwrap();
+    McpServers::from_clients([client])
+}
+
+#[tokio::test]
+async fn registers_server_tools_with_prefixed_names() {
+    let servers = connect().await;
+    let toolset = servers
+        .register(AsyncToolSet::<NoContext>::new())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        toolset.tool_names(),
+        vec!["fake__echo", "fake__fail", "fake__grow"]
+    );
+    let output = toolset
+        .try_tool_call(NoContext(), "fake__echo", r#"{"text": "hello"}"#)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.text, "hello");
+}
+
+#[tokio::test]
+async fn validates_arguments_against_the_server_schema() {
+    let servers = connect().await;
+    let toolset = servers
+        .register(AsyncToolSet::<NoContext>::new())
+        .await
+        .unwrap();
+
+    let result = toolset
+        .try_tool_call(NoContext(), "fake__echo", r#"{"text": 3}"#)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn maps_tool_errors_to_error_status() {
+    let servers = connect().await;
+    let toolset = servers
+        .register(AsyncToolSet::<NoContext>::new().with_approver(ApproveAll))
+        .await
+        .unwrap();
+
+    let output = toolset
+        .try_tool_call(NoContext(), "fake__fail", "{}")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.status, ToolStatus::Error);
+    assert_eq!(output.text, "it broke");
+}
+
+#[tokio::test]
+async fn refreshes_tools_after_a_list_changed_notification() {
+    let servers = connect().await;
+    let mut toolset = servers
+        .register(AsyncToolSet::<NoContext>::new().with_approver(ApproveAll))
+        .await
+        .unwrap();
+
+    toolset
+        .try_tool_call(NoContext(), "fake__grow", "{}")
+        .await
+        .unwrap()
+        .unwrap();
+    servers.refresh(&mut toolset).await.unwrap();
+
+    assert_eq!(
+        toolset.tool_names(),
+        vec!["fake__count", "fake__echo", "fake__fail", "fake__grow"]
+    );
+    assert_eq!(toolset.schemas.len(), 4);
+}
+
+#[tokio::test]
+async fn deny_all_blocks_tools_not_marked_read_only() {
+    let servers = connect().await;
+    let toolset = servers
+        .register(
+            AsyncToolSet::<NoContext>::new()
+                .with_approver(PolicyApprover::new(ApprovalPolicy::DenyAll, ApproveAll)),
+        )
+        .await
+        .unwrap();
+    assert!(!toolset.is_mutating("fake__echo"));
+    assert!(toolset.is_mutating("fake__fail"));
+
+    let denied = toolset
+        .try_tool_call(NoContext(), "fake__fail", "{}")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(denied.status, ToolStatus::Denied);
+    let echoed = toolset
+        .try_tool_call(NoContext(), "fake__echo", r#"{"text": "hi"}"#)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(echoed.text, "hi");
+}
+
+#[tokio::test]
+async fn config_overrides_tool_annotations() {
+    let (client_io, server_io) = tokio::io::duplex(4096);
+    tokio::spawn(fake_server(server_io));
+    let (reader, writer) = tokio::io::split(client_io);
+    let client = McpClient::from_io("fake", reader, writer)
+        .await
+        .unwrap()
+        .with_mutating(Some(false));
+    let toolset = McpServers::from_clients([client])
+        .register(AsyncToolSet::<NoContext>::new())
+        .await
+        .unwrap();
+
+    assert!(!toolset.is_mutating("fake__fail"));
+}
+
+#[tokio::test]
+async fn cancelled_calls_are_cancelled_on_the_server() {
+    let (client_io, server_io) = tokio::io::duplex(4096);
+    // answers `initialize` and reports the id of the first request it is
+    // told to cancel
+    let server = tokio::spawn(async move {
+        let (reader, mut writer) = tokio::io::split(server_io);
+        let mut lines = BufReader::new(reader).lines();
+        let mut called = None;
+        while let Ok(Some(line)) = lines.next_line().await {
+            let message: RpcMessage = serde_json::from_str(&line).unwrap();
+            match (message.method.as_deref(), message.id) {
+                (Some("initialize"), Some(id)) => {
+                    let response = RpcMessage::response(id, json!({}));
+                    let line = serde_json::to_string(&response).unwrap() + "\n";
+                    writer.write_all(line.as_bytes()).await.unwrap();
+                }
+                (Some("tools/call"), id) => called = id,
+                (Some("notifications/cancelled"), None) => {
+                    return (called, message.params.unwrap()["requestId"].clone());
+                }
+                _ => {}
+            }
+        }
+        panic!("the call was not cancelled");
+    });
+    let (reader, writer) = tokio::io::split(client_io);
+    let client = McpClient::from_io("slow", reader, writer).await.unwrap();
+
+    let cancel = CancellationToken::new();
+    let call = client.call_tool_with_cancel("wait", json!({}), &cancel);
+    let cancel_soon = async {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        cancel.cancel();
+    };
+    let (result, ()) = tokio::join!(call, cancel_soon);
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<ToolInterrupted>(),
+        Some(ToolInterrupted::Cancelled)
+    ));
+    let (called, cancelled) = server.await.unwrap();
+    assert_eq!(called, Some(cancelled));
+}
+
+/// Connects to `tests/fixtures/mcp_server.sh`, which crashes on every call,
+/// and returns the file it records its starts in.
+async fn crashing_server(name: &str) -> (McpClient, std::path::PathBuf) {
+    let launches = std::env::temp_dir().join(format!("hex-mcp-{name}-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&launches);
+    let config = McpServerConfig {
+        command: Some("sh".to_string()),
+        args: vec!["tests/fixtures/mcp_server.sh".to_string()],
+        env: BTreeMap::from([("LAUNCHES".to_string(), launches.display().to_string())]),
+        ..Default::default()
+    };
+    (McpClient::connect(name, &config).await.unwrap(), launches)
+}
+
+#[tokio::test]
+async fn reports_the_stderr_of_stopped_servers() {
+    let (client, launches) = crashing_server("stderr").await;
+
+    let err = client.call_tool("anything", json!({})).await.unwrap_err();
+
+    let message = format!("{err:#}");
+    assert!(message.contains("crashed on purpose"), "{message}");
+    std::fs::remove_file(launches).unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_calls_restart_a_stopped_server_once() {
+    let (client, launches) = crashing_server("restart").await;
+
+    let (first, second) = tokio::join!(
+        client.call_tool("anything", json!({})),
+        client.call_tool("anything", json!({}))
+    );
+
+    assert!(first.is_err() && second.is_err());
+    assert_eq!(
+        std::fs::read_to_string(&launches).unwrap().lines().count(),
+        2
+    );
+    assert!(client.take_tools_changed());
+    std::fs::remove_file(launches).unwrap();
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct Shout {
+    #[schemars(description = "text to shout")]
+    text: String,
+}
+
+#[tokio::test]
+async fn serves_a_toolset_to_mcp_clients() {
+    let toolset = AsyncToolSet::<NoContext>::new()
+        .add_fn(
+            "shout",
+            "upper-case some text",
+            async |args: Shout, _, _| {
+                if args.text.is_empty() {
+                    anyhow::bail!("nothing to shout");
+                }
+                Ok(ToolOutput::ok(args.text.to_uppercase()))
+            },
+        )
+        .unwrap();
+    let (client_io, server_io) = tokio::io::duplex(4096);
+    let (server_reader, server_writer) = tokio::io::split(server_io);
+    let server = McpServer::new(toolset, NoContext());
+    // toolsets aren't `Send`, so the server runs alongside the client
+    let client = async move {
+        let (reader, writer) = tokio::io::split(client_io);
+        let client = McpClient::from_io("hex", reader, writer).await.unwrap();
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "shout");
+        assert_eq!(tools[0].input_schema["required"], json!(["text"]));
+
+        let output = client
+            .call_tool("shout", json!({ "text": "hi" }))
+            .await
+            .unwrap();
+        assert_eq!(output, ToolOutput::ok("HI"));
+
+        let failed = client
+            .call_tool("shout", json!({ "text": "" }))
+            .await
+            .unwrap();
+        assert_eq!(failed, ToolOutput::error("nothing to shout"));
+
+        let invalid = client
+            .call_tool("shout", json!({ "text": 1 }))
+            .await
+            .unwrap();
+        assert_eq!(invalid.status, ToolStatus::Error);
+        assert!(
+            invalid
+                .text
+                .starts_with("arguments for 'shout' do not match its schema:"),
+            "{}",
+            invalid.text
+        );
+        assert!(invalid.data.unwrap()["violations"].is_array());
+        assert!(client.call_tool("whisper", json!({})).await.is_err());
+    };
+    let (served, ()) = tokio::join!(server.serve(server_reader, server_writer), client);
+    served.unwrap();
+}
+
+#[tokio::test]
+async fn imports_tools_from_hex_mcp_serve() {
+    let config = McpServerConfig {
+        command: Some(env!("CARGO_BIN_EXE_hex").to_string()),
+        args: vec!["mcp-serve".to_string()],
+        ..Default::default()
+    };
+    let servers = McpServers::connect(&BTreeMap::from([("hex".to_string(), config)]))
+        .await
+        .unwrap();
+    let toolset = servers
+        .register(AsyncToolSet::<NoContext>::new())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        toolset.tool_names(),
+        vec![
+            "hex__create_file",
+            "hex__edit_file",
+            "hex__list_directory",
+            "hex__read_files"
+        ]
+    );
+    assert!(toolset.is_mutating("hex__edit_file"));
+    assert!(!toolset.is_mutating("hex__list_directory"));
+    let output = toolset
+        .try_tool_call(
+            NoContext(),
+            "hex__list_directory",
+            r#"{"path": "hex-derive"}"#,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(output.text.contains("hex-derive/Cargo.toml"));
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::ApproveAll;
+use hex::config::PluginConfig;
+use hex::plugins::register_plugins;
+use hex::types::{AsyncToolSet, CancellationToken, NoContext, ToolInterrupted, ToolOutput};
+
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+async fn toolset(timeout_secs: Option<u64>) -> AsyncToolSet<NoContext> {
+    let config = PluginConfig {
+        command: "sh".to_string(),
+        args: vec!["tests/fixtures/plugin.sh".to_string()],
+        timeout_secs,
+        ..Default::default()
+    };
+    register_plugins(
+        &BTreeMap::from([("test".to_string(), config)]),
+        AsyncToolSet::new().with_approver(ApproveAll),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn registers_and_calls_plugin_tools() {
+    let toolset = toolset(None).await;
+    assert_eq!(
+        toolset.tool_names(),
+        vec!["test__crash", "test__nap", "test__shout"]
+    );
+
+    let output = toolset
+        .try_tool_call(NoContext(), "test__shout", r#"{"text": "hello"}"#)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        output,
+        ToolOutput::ok("HELLO").with_data(json!({ "length": 5, "stderr": "shouting" }))
+    );
+}
+
+#[tokio::test]
+async fn treats_tools_as_mutating_unless_the_manifest_says_otherwise() {
+    let toolset = toolset(None).await;
+    assert!(!toolset.is_mutating("test__shout"));
+    assert!(toolset.is_mutating("test__nap"));
+
+    let config = PluginConfig {
+        command: "sh".to_string(),
+        args: vec!["tests/fixtures/plugin.sh".to_string()],
+        mutating: Some(false),
+        ..Default::default()
+    };
+    let toolset = register_plugins(
+        &BTreeMap::from([("test".to_string(), config)]),
+        AsyncToolSet::<NoContext>::new(),
+    )
+    .await
+    .unwrap();
+    assert!(!toolset.is_mutating("test__nap"));
+}
+
+#[tokio::test]
+async fn reports_stderr_of_failed_calls() {
+    let err = toolset(None)
+        .await
+        .try_tool_call(NoContext(), "test__crash", "{}")
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    let message = format!("{err:#}");
+    assert!(message.starts_with("plugin 'test' failed"), "{message}");
+    assert!(message.contains("something went wrong"), "{message}");
+}
+
+#[tokio::test]
+async fn kills_calls_that_time_out() {
+    let err = toolset(Some(1))
+        .await
+        .try_tool_call(NoContext(), "test__nap", "{}")
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    assert!(format!("{err:#}").contains("timed out after 1s"));
+}
+
+#[tokio::test]
+async fn kills_calls_that_are_cancelled() {
+    let toolset = toolset(None).await;
+    let cancel = CancellationToken::new();
+    let call = toolset.try_tool_call_with_cancel(NoContext(), "test__nap", "{}", cancel.clone());
+    let cancel_soon = async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+    };
+    let started = Instant::now();
+    let (result, ()) = tokio::join!(call, cancel_soon);
+
+    let err = result.unwrap().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ToolInterrupted>(),
+        Some(ToolInterrupted::Cancelled)
+    ));
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn fails_to_load_a_missing_plugin() {
+    let config = PluginConfig {
+        command: "hex-test-no-such-plugin".to_string(),
+        ..Default::default()
+    };
+    let result = register_plugins(
+        &BTreeMap::from([("missing".to_string(), config)]),
+        AsyncToolSet::<NoContext>::new(),
+    )
+    .await;
+
+    let Err(err) = result else {
+        panic!("expected an error");
+    };
+    assert!(format!("{err:#}").starts_with("could not load plugin 'missing'"));
+}
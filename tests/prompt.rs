@@ -0,0 +1,59 @@
+mod common;
+
+use common::TempDir;
+use hex::config::{DEFAULT_SYSTEM_PROMPT, PromptSection, SystemPrompt};
+
+#[test]
+fn uses_the_built_in_default_without_instruction_files() {
+    let dir = TempDir::new("default");
+
+    let prompt = SystemPrompt::assemble(None, None, dir.path()).unwrap();
+
+    assert_eq!(prompt.sections.len(), 1);
+    assert!(prompt.text().starts_with(DEFAULT_SYSTEM_PROMPT));
+    assert!(prompt.text().contains(&dir.path().display().to_string()));
+}
+
+#[test]
+fn configured_base_replaces_the_default() {
+    let dir = TempDir::new("base");
+
+    let prompt = SystemPrompt::assemble(Some("be terse"), None, dir.path()).unwrap();
+
+    assert_eq!(prompt.text(), "be terse");
+}
+
+#[test]
+fn appends_user_then_nearest_project_instructions() {
+    let user = TempDir::new("user");
+    let project = TempDir::new("project");
+    let nested = project.path().join("src").join("deep");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(user.path().join("HEX.md"), "prefer tabs\n").unwrap();
+    std::fs::write(project.path().join("HEX.md"), "run cargo fmt\n").unwrap();
+
+    let prompt = SystemPrompt::assemble(Some("base"), Some(user.path()), &nested).unwrap();
+
+    assert_eq!(
+        prompt.sections,
+        vec![
+            PromptSection::Base("base".to_string()),
+            PromptSection::Instructions {
+                path: user.path().join("HEX.md"),
+                text: "prefer tabs\n".to_string(),
+            },
+            PromptSection::Instructions {
+                path: project.path().join("HEX.md"),
+                text: "run cargo fmt\n".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        prompt.text(),
+        format!(
+            "base\n\n# Instructions from {}\n\nprefer tabs\n\n# Instructions from {}\n\nrun cargo fmt",
+            user.path().join("HEX.md").display(),
+            project.path().join("HEX.md").display()
+        )
+    );
+}
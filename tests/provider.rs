@@ -0,0 +1,80 @@
+use hex::config::{Config, ConfigLayer, Source};
+use hex::provider::{Message, ProviderBuilder};
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// Sends one chat request to a local server that fails it, and returns the
+/// request's lower-cased headers and JSON body.
+async fn capture_request(builder: ProviderBuilder) -> (Vec<String>, Value) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut reader = BufReader::new(socket);
+        let mut headers = vec![];
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let line = line.trim_end().to_ascii_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            headers.push(line);
+        }
+        let length = headers
+            .iter()
+            .find_map(|header| header.strip_prefix("content-length: "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await.unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        (headers, serde_json::from_slice(&body).unwrap())
+    });
+
+    let provider = builder.api_base(api_base).build().unwrap();
+    let messages = [Message::User {
+        content: "hi".to_string(),
+    }];
+    let _ = provider.stream_chat(&messages, &[]).await;
+    server.await.unwrap()
+}
+
+#[tokio::test]
+async fn sends_max_tokens_to_compatible_servers() {
+    let builder = ProviderBuilder::default().max_tokens(64);
+
+    let (_, body) = capture_request(builder).await;
+
+    assert_eq!(body["max_tokens"], 64);
+    assert!(body.get("max_completion_tokens").is_none());
+}
+
+#[tokio::test]
+async fn forwards_the_configured_api_key_and_headers() {
+    let layer = ConfigLayer::from_toml(
+        r#"
+        api_key = "secret"
+        headers = { "X-Team" = "hex" }
+        "#,
+    )
+    .unwrap();
+    let config = Config::resolve([(Source::Flag, layer)]);
+    assert_eq!(
+        config.headers,
+        BTreeMap::from([("X-Team".to_string(), "hex".to_string())])
+    );
+
+    let (headers, _) = capture_request(config.provider_builder()).await;
+
+    assert!(headers.contains(&"authorization: bearer secret".to_string()));
+    assert!(headers.contains(&"x-team: hex".to_string()));
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use common::TempDir;
+use hex::provider::{Message, ToolCall};
+use hex::session::{Session, read_transcript};
+use hex::types::ToolStatus;
+
+fn turn() -> Vec<Message> {
+    vec![
+        Message::System {
+            content: "not recorded".to_string(),
+        },
+        Message::User {
+            content: "list src".to_string(),
+        },
+        Message::Assistant {
+            content: None,
+            tool_calls: vec![ToolCall {
+                id: "call_1".to_string(),
+                name: "list_directory".to_string(),
+                json: r#"{"path": "src"}"#.to_string(),
+            }],
+        },
+        Message::Tool {
+            tool_call_id: "call_1".to_string(),
+            content: "src/main.rs".to_string(),
+            status: ToolStatus::Ok,
+        },
+        Message::Assistant {
+            content: Some("There is one file.".to_string()),
+            tool_calls: vec![],
+        },
+    ]
+}
+
+#[test]
+fn records_every_non_system_message_with_model_and_timestamp() {
+    let dir = TempDir::new("record");
+    let mut session = Session::create(dir.path(), Some("gpt-4.1".to_string())).unwrap();
+
+    session.append(&turn()).unwrap();
+
+    let records = read_transcript(session.path()).unwrap();
+    assert_eq!(records.len(), 4);
+    assert!(
+        records
+            .iter()
+            .all(|record| record.model.as_deref() == Some("gpt-4.1"))
+    );
+    assert!(
+        records
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp)
+    );
+    assert_eq!(
+        records
+            .into_iter()
+            .map(|record| record.message)
+            .collect::<Vec<_>>(),
+        turn()[1..]
+    );
+}
+
+#[test]
+fn resumes_history_and_keeps_appending() {
+    let dir = TempDir::new("resume");
+    let mut session = Session::create(dir.path(), None).unwrap();
+    session.append(&turn()).unwrap();
+    let id = session.id().to_string();
+    drop(session);
+
+    let (mut resumed, history) = Session::resume(dir.path(), &id, None).unwrap();
+    assert_eq!(history, turn()[1..]);
+
+    resumed
+        .append(&[Message::User {
+            content: "thanks".to_string(),
+        }])
+        .unwrap();
+    let (_, history) = Session::resume(dir.path(), &id, None).unwrap();
+    assert_eq!(history.len(), 5);
+    assert!(Session::resume(dir.path(), "20250101-120000-00ff", None).is_err());
+}
+
+#[test]
+fn rejects_resume_ids_that_are_not_session_ids() {
+    let dir = TempDir::new("resume-ids");
+    let sessions = dir.path().join("sessions");
+    let session = Session::create(&sessions, None).unwrap();
+    std::fs::write(dir.path().join("secret.jsonl"), "").unwrap();
+
+    for id in [
+        "../secret",
+        "/tmp/secret",
+        "20250101-120000-00ff/..",
+        "20250101-120000-00FF",
+        "20250101-120000",
+        "secret",
+    ] {
+        let Err(err) = Session::resume(&sessions, id, None) else {
+            panic!("resumed '{id}'");
+        };
+        assert!(err.to_string().starts_with("invalid session id"), "{err}");
+    }
+    assert!(Session::resume(&sessions, session.id(), None).is_ok());
+}
+
+#[test]
+fn sessions_created_at_once_get_their_own_transcripts() {
+    let dir = TempDir::new("unique");
+    let sessions = (0..200)
+        .map(|_| Session::create(dir.path(), None).unwrap())
+        .collect::<Vec<_>>();
+
+    let mut ids = sessions.iter().map(Session::id).collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), sessions.len());
+    assert_eq!(Session::list(dir.path()).unwrap().len(), sessions.len());
+}
+
+#[test]
+fn lists_sessions_and_finds_the_latest() {
+    let dir = TempDir::new("list");
+    assert_eq!(Session::latest(dir.path()).unwrap(), None);
+    assert!(Session::list(dir.path()).unwrap().is_empty());
+
+    let mut session = Session::create(dir.path(), None).unwrap();
+    session.append(&turn()).unwrap();
+
+    let summaries = Session::list(dir.path()).unwrap();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].id, session.id());
+    assert_eq!(summaries[0].messages, 4);
+    assert_eq!(summaries[0].first_prompt.as_deref(), Some("list src"));
+    assert_eq!(
+        Session::latest(dir.path()).unwrap().as_deref(),
+        Some(session.id())
+    );
+}
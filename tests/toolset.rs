@@ -0,0 +1,456 @@
+use hex::offline_tools::offline_toolset;
+use hex::types::{
+    AsyncToolSet, CancellationToken, DynamicTool, NoContext, SyncToolSet, Tool, ToolCallError,
+    ToolInterrupted, ToolOutput, ToolSetCreationError, sync_tool_cancelled,
+};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn selects_tools_by_normalized_name() {
+    let toolset = offline_toolset()
+        .select(&["read_files", "list-directory"])
+        .unwrap();
+
+    assert_eq!(toolset.tool_names(), vec!["list_directory", "read_files"]);
+    assert_eq!(toolset.schemas.len(), 2);
+}
+
+#[test]
+fn selecting_no_tools_empties_the_toolset() {
+    let toolset = offline_toolset().select::<&str>(&[]).unwrap();
+
+    assert!(toolset.tool_names().is_empty());
+    assert!(toolset.tool_definitions().is_empty());
+}
+
+#[test]
+fn marks_file_changing_tools_mutating_and_sequential() {
+    let toolset = offline_toolset();
+
+    assert!(toolset.is_sequential("edit_file"));
+    assert!(toolset.is_sequential("create_file"));
+    assert!(!toolset.is_sequential("read_files"));
+    assert!(toolset.is_mutating("edit_file"));
+    assert!(toolset.is_mutating("create_file"));
+    assert!(!toolset.is_mutating("list_directory"));
+    assert!(
+        offline_toolset()
+            .sequential(&["delete_everything"])
+            .is_err()
+    );
+}
+
+#[test]
+fn rejects_unknown_tool_names() {
+    let Err(ToolCallError::NotFound(name)) = offline_toolset().select(&["delete_everything"])
+    else {
+        panic!("expected an unknown tool error");
+    };
+    assert_eq!(name, "delete_everything");
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "search for text")]
+struct Search {
+    #[schemars(description = "text to find")]
+    query: String,
+    #[schemars(description = "where to look")]
+    scope: Option<Scope>,
+    #[serde(default)]
+    #[schemars(description = "maximum number of results")]
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "a directory and file pattern")]
+struct Scope {
+    #[schemars(description = "directory to search")]
+    directory: String,
+}
+
+impl Tool for Search {
+    type Context = NoContext;
+    fn apply(&self, _: Self::Context) -> anyhow::Result<ToolOutput> {
+        let scope = self.scope.as_ref().map_or(".", |scope| &scope.directory);
+        Ok(ToolOutput::ok(format!(
+            "{} in {scope} (limit {:?})",
+            self.query, self.limit
+        )))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "set environment variables")]
+struct SetEnv {
+    #[schemars(description = "variables to set")]
+    vars: HashMap<String, String>,
+}
+
+impl Tool for SetEnv {
+    type Context = NoContext;
+    fn apply(&self, _: Self::Context) -> anyhow::Result<ToolOutput> {
+        Ok(ToolOutput::ok(format!("{} set", self.vars.len())))
+    }
+}
+
+#[test]
+fn strict_toolsets_normalize_schemas() {
+    let toolset = SyncToolSet::<NoContext>::strict()
+        .add_tool::<Search>()
+        .unwrap();
+
+    let definition = &toolset.tool_definitions()[0];
+    assert!(definition.strict);
+    assert_eq!(
+        definition.parameters,
+        json!({
+            "description": "search for text",
+            "type": "object",
+            "properties": {
+                "limit": {
+                    "description": "maximum number of results",
+                    "type": ["integer", "null"],
+                    "minimum": 0.0
+                },
+                "query": { "description": "text to find", "type": "string" },
+                "scope": {
+                    "description": "where to look",
+                    "anyOf": [
+                        {
+                            "description": "a directory and file pattern",
+                            "type": "object",
+                            "properties": {
+                                "directory": { "description": "directory to search", "type": "string" }
+                            },
+                            "required": ["directory"],
+                            "additionalProperties": false
+                        },
+                        { "type": "null" }
+                    ]
+                }
+            },
+            "required": ["limit", "query", "scope"],
+            "additionalProperties": false
+        })
+    );
+}
+
+#[test]
+fn strict_toolsets_accept_null_optionals() {
+    let toolset = SyncToolSet::<NoContext>::strict()
+        .add_tool::<Search>()
+        .unwrap();
+
+    let output = toolset
+        .try_tool_call(
+            NoContext(),
+            "Search",
+            r#"{"query": "todo", "scope": null, "limit": null}"#,
+        )
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(output.text, "todo in . (limit None)");
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "store a value")]
+struct Store {
+    #[schemars(description = "any JSON value")]
+    value: serde_json::Value,
+    #[schemars(description = "a note about it")]
+    note: Option<String>,
+}
+
+impl Tool for Store {
+    type Context = NoContext;
+    fn apply(&self, _: Self::Context) -> anyhow::Result<ToolOutput> {
+        Ok(ToolOutput::ok(format!("{} ({:?})", self.value, self.note)))
+    }
+}
+
+#[test]
+fn strict_toolsets_keep_nulls_inside_values() {
+    let toolset = SyncToolSet::<NoContext>::strict()
+        .add_tool::<Store>()
+        .unwrap();
+    let call = |arguments| {
+        toolset
+            .try_tool_call(NoContext(), "Store", arguments)
+            .unwrap()
+            .unwrap()
+            .text
+    };
+
+    assert_eq!(call(r#"{"value": null, "note": null}"#), "null (None)");
+    assert_eq!(
+        call(r#"{"value": {"key": null}, "note": "kept"}"#),
+        r#"{"key":null} (Some("kept"))"#
+    );
+}
+
+#[test]
+fn strict_toolsets_reject_maps() {
+    let Err(ToolSetCreationError::NotStrict(name, _)) =
+        SyncToolSet::<NoContext>::strict().add_tool::<SetEnv>()
+    else {
+        panic!("expected a strict mode error");
+    };
+    assert_eq!(name, "SetEnv");
+    assert!(SyncToolSet::<NoContext>::new().add_tool::<SetEnv>().is_ok());
+}
+
+fn greet_tool() -> DynamicTool<NoContext> {
+    DynamicTool::new(
+        "greet",
+        "greet someone by name",
+        json!({
+            "type": "object",
+            "properties": { "name": { "type": "string", "minLength": 1 } },
+            "required": ["name"]
+        }),
+        |arguments: serde_json::Value, _, _| async move {
+            Ok(ToolOutput::ok(format!(
+                "hello {}",
+                arguments["name"].as_str().unwrap()
+            )))
+        },
+    )
+}
+
+#[tokio::test]
+async fn dispatches_dynamic_tools() {
+    let toolset = AsyncToolSet::new().add_dynamic(greet_tool()).unwrap();
+
+    let tools = toolset.openai_chatcompletion_toolset();
+    assert_eq!(tools[0].function.name, "greet");
+    assert_eq!(
+        tools[0].function.parameters.as_ref().unwrap()["required"],
+        json!(["name"])
+    );
+    let output = toolset
+        .try_tool_call(NoContext(), "greet", r#"{"name": "ada"}"#)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(output.text, "hello ada");
+    assert!(matches!(
+        toolset
+            .try_tool_call(NoContext(), "greet", r#"{"name": ""}"#)
+            .await,
+        Err(ToolCallError::InvalidArguments(_))
+    ));
+}
+
+#[test]
+fn rejects_dynamic_tools_without_object_arguments() {
+    let tool = DynamicTool::new(
+        "count",
+        "takes a bare number",
+        json!({ "type": "integer" }),
+        |_, _: &NoContext, _| async { Ok(ToolOutput::ok("")) },
+    );
+
+    assert!(matches!(
+        AsyncToolSet::new().add_dynamic(tool),
+        Err(ToolSetCreationError::Validation(_))
+    ));
+    assert!(
+        AsyncToolSet::new()
+            .add_dynamic(greet_tool())
+            .unwrap()
+            .add_dynamic(greet_tool())
+            .is_err()
+    );
+}
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "block the calling thread")]
+struct Block {
+    #[schemars(description = "what to do: sleep, panic or wait_for_cancel")]
+    action: String,
+}
+
+impl Tool for Block {
+    type Context = Log;
+    fn apply(&self, log: Self::Context) -> anyhow::Result<ToolOutput> {
+        match self.action.as_str() {
+            "panic" => panic!("boom"),
+            "wait_for_cancel" => {
+                for _ in 0..200 {
+                    if sync_tool_cancelled() {
+                        log.lock().unwrap().push("cancelled".to_string());
+                        break;
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            }
+            _ => std::thread::sleep(Duration::from_millis(200)),
+        }
+        log.lock().unwrap().push(self.action.clone());
+        Ok(ToolOutput::ok(""))
+    }
+}
+
+fn block_toolset() -> AsyncToolSet<Log> {
+    SyncToolSet::new().add_tool::<Block>().unwrap().into_async()
+}
+
+#[tokio::test]
+async fn sync_tools_run_off_the_runtime_thread() {
+    let toolset = block_toolset();
+    let log = Log::default();
+
+    let blocking = toolset.try_tool_call(log.clone(), "Block", r#"{"action": "sleep"}"#);
+    let timer = async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        log.lock().unwrap().push("timer".to_string());
+    };
+    let (output, ()) = tokio::join!(blocking, timer);
+
+    assert!(output.unwrap().is_ok());
+    assert_eq!(*log.lock().unwrap(), vec!["timer", "sleep"]);
+}
+
+#[tokio::test]
+async fn sync_tool_panics_become_errors() {
+    let err = block_toolset()
+        .try_tool_call(Log::default(), "Block", r#"{"action": "panic"}"#)
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    assert_eq!(err.to_string(), "the tool panicked: boom");
+}
+
+#[tokio::test]
+async fn dropped_sync_tool_calls_are_flagged_as_cancelled() {
+    let toolset = block_toolset();
+    let log = Log::default();
+
+    let call = toolset.try_tool_call(log.clone(), "Block", r#"{"action": "wait_for_cancel"}"#);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(20), call)
+            .await
+            .is_err()
+    );
+    for _ in 0..100 {
+        if log.lock().unwrap().len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    assert_eq!(*log.lock().unwrap(), vec!["cancelled", "wait_for_cancel"]);
+}
+
+#[tokio::test]
+async fn timed_out_sync_tools_fail_and_are_flagged_as_cancelled() {
+    let toolset = block_toolset()
+        .timeout(&["Block"], Duration::from_millis(20))
+        .unwrap();
+    let log = Log::default();
+
+    let err = toolset
+        .try_tool_call(log.clone(), "Block", r#"{"action": "wait_for_cancel"}"#)
+        .await
+        .unwrap()
+        .unwrap_err();
+    for _ in 0..100 {
+        if log.lock().unwrap().len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    assert!(matches!(
+        err.downcast_ref::<ToolInterrupted>(),
+        Some(ToolInterrupted::TimedOut(limit)) if *limit == Duration::from_millis(20)
+    ));
+    assert_eq!(*log.lock().unwrap(), vec!["cancelled", "wait_for_cancel"]);
+}
+
+#[tokio::test]
+async fn cancelled_calls_fail_without_running_the_tool() {
+    let log = Log::default();
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    let err = block_toolset()
+        .try_tool_call_with_cancel(log.clone(), "Block", r#"{"action": "sleep"}"#, cancel)
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<ToolInterrupted>(),
+        Some(ToolInterrupted::Cancelled)
+    ));
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert!(log.lock().unwrap().is_empty());
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct Nothing {}
+
+#[tokio::test]
+async fn function_tools_see_timeouts_through_their_token() {
+    let seen = Arc::new(Mutex::new(None::<CancellationToken>));
+    let toolset = AsyncToolSet::new()
+        .add_fn("wait", "wait until cancelled", {
+            let seen = seen.clone();
+            async move |_: Nothing, _: &NoContext, cancel: CancellationToken| {
+                *seen.lock().unwrap() = Some(cancel.clone());
+                cancel.cancelled().await;
+                Ok(ToolOutput::ok(""))
+            }
+        })
+        .unwrap()
+        .timeout(&["wait"], Duration::from_millis(20))
+        .unwrap();
+
+    let err = toolset
+        .try_tool_call(NoContext(), "wait", "{}")
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<ToolInterrupted>(),
+        Some(ToolInterrupted::TimedOut(_))
+    ));
+    assert!(seen.lock().unwrap().as_ref().unwrap().is_cancelled());
+}
+
+#[tokio::test]
+async fn function_tools_borrow_the_context_across_awaits() {
+    let toolset = AsyncToolSet::new()
+        .add_fn(
+            "note",
+            "record a note later",
+            async |_: Nothing, log: &Log, _| {
+                tokio::task::yield_now().await;
+                log.lock().unwrap().push("noted".to_string());
+                Ok(ToolOutput::ok(log.lock().unwrap().len().to_string()))
+            },
+        )
+        .unwrap();
+    let log = Log::default();
+
+    let output = toolset
+        .try_tool_call(log.clone(), "note", "{}")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(output.text, "1");
+    assert_eq!(*log.lock().unwrap(), vec!["noted"]);
+}